@@ -0,0 +1,188 @@
+// ========== ICC 色彩管理 ==========
+// 读取照片内嵌的 ICC 配置文件（JPEG APP2 / PNG iCCP），在照片色彩空间和 sRGB 之间转换像素：
+// 预览纹理和界面颜色按 sRGB 显示，导出时按用户选择转换为 sRGB 或保留原配置文件。
+// 没有内嵌配置文件或配置文件就是 sRGB 时按 sRGB 处理，不做任何转换。
+
+use image::{DynamicImage, ImageBuffer, Pixel};
+use moxcms::{ColorProfile, DataColorSpace, Layout, ProfileText, ToneReprCurve, TransformOptions, Xyzd};
+
+// 与 sRGB 原色比较时允许的误差（各厂商生成的 sRGB 配置文件数值略有差异）
+const SRGB_TOLERANCE: f64 = 0.002;
+// 与 sRGB 色调曲线比较时允许的误差（线性值）和采样点数：查找表形式的 sRGB 曲线与公式略有差异
+const TRC_TOLERANCE: f32 = 0.005;
+const TRC_SAMPLES: usize = 64;
+
+/// 照片内嵌的非 sRGB 的 RGB 配置文件
+pub struct IccProfile {
+    data: Vec<u8>,        // 原始配置文件数据，保留配置文件导出时原样写入
+    profile: ColorProfile,
+    pub name: String,     // 配置文件描述（如 Display P3、Adobe RGB (1998)）
+}
+
+/**
+ * 解析内嵌的 ICC 配置文件
+ * @param data - 配置文件数据
+ * @return 需要转换的配置文件；无法解析、不是 RGB 配置文件或就是 sRGB 时返回 None
+ */
+pub fn parse(data: Vec<u8>) -> Option<IccProfile> {
+    let profile = match ColorProfile::new_from_slice(&data) {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("无法解析ICC配置文件: {:?}", e);
+            return None;
+        }
+    };
+    if profile.color_space != DataColorSpace::Rgb {
+        eprintln!("不支持的ICC配置文件色彩空间: {:?}，按 sRGB 处理", profile.color_space);
+        return None;
+    }
+    if is_srgb(&profile) {
+        return None;
+    }
+    let name = profile.description.as_ref().map(profile_text).unwrap_or_else(|| "未命名配置文件".to_string());
+    Some(IccProfile { data, profile, name })
+}
+
+impl IccProfile {
+    /// 原始配置文件数据
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 把照片色彩空间的图片转换为 sRGB（保留位深）
+    pub fn to_srgb(&self, image: &DynamicImage) -> DynamicImage {
+        convert(image, &self.profile, &ColorProfile::new_srgb())
+    }
+
+    /// 把 sRGB 图片转换为照片的色彩空间（保留位深）
+    pub fn srgb_to_source(&self, image: &DynamicImage) -> DynamicImage {
+        convert(image, &ColorProfile::new_srgb(), &self.profile)
+    }
+
+    /// 把照片色彩空间的颜色（主色、调色板）转换为 sRGB
+    pub fn colors_to_srgb(&self, colors: &mut [[u8; 3]]) {
+        let source: Vec<u8> = colors.iter().flatten().copied().collect();
+        let mut target = vec![0u8; source.len()];
+        let result = self
+            .profile
+            .create_transform_8bit(Layout::Rgb, &ColorProfile::new_srgb(), Layout::Rgb, TransformOptions::default())
+            .and_then(|transform| transform.transform(&source, &mut target));
+        match result {
+            Ok(()) => {
+                for (color, chunk) in colors.iter_mut().zip(target.chunks_exact(3)) {
+                    color.copy_from_slice(chunk);
+                }
+            }
+            Err(e) => eprintln!("颜色转换失败: {:?}", e),
+        }
+    }
+}
+
+// 原色和色调曲线都与 sRGB 一致的配置文件视为 sRGB（相机和编辑软件写入的 sRGB 配置文件不必转换）；
+// 原色相同但曲线不同（如线性或 2.2 伽马）时仍需转换
+fn is_srgb(profile: &ColorProfile) -> bool {
+    let srgb = ColorProfile::new_srgb();
+    let close = |a: Xyzd, b: Xyzd| {
+        (a.x - b.x).abs() < SRGB_TOLERANCE && (a.y - b.y).abs() < SRGB_TOLERANCE && (a.z - b.z).abs() < SRGB_TOLERANCE
+    };
+    close(profile.red_colorant, srgb.red_colorant)
+        && close(profile.green_colorant, srgb.green_colorant)
+        && close(profile.blue_colorant, srgb.blue_colorant)
+        && [&profile.red_trc, &profile.green_trc, &profile.blue_trc]
+            .into_iter()
+            .all(|curve| curve.as_ref().is_some_and(|curve| same_curve(curve, srgb.red_trc.as_ref())))
+}
+
+// 在 0 ~ 1 之间均匀采样比较两条色调曲线，无法计算时视为不同
+fn same_curve(curve: &ToneReprCurve, reference: Option<&ToneReprCurve>) -> bool {
+    let (Some(reference), Ok(curve)) = (reference, curve.make_linear_evaluator()) else {
+        return false;
+    };
+    let Ok(reference) = reference.make_linear_evaluator() else {
+        return false;
+    };
+    (0..=TRC_SAMPLES).all(|i| {
+        let value = i as f32 / TRC_SAMPLES as f32;
+        (curve.evaluate_value(value) - reference.evaluate_value(value)).abs() < TRC_TOLERANCE
+    })
+}
+
+// 配置文件描述文字
+fn profile_text(text: &ProfileText) -> String {
+    match text {
+        ProfileText::PlainString(value) => value.clone(),
+        ProfileText::Localizable(values) => values.first().map(|value| value.value.clone()).unwrap_or_default(),
+        ProfileText::Description(description) => description.ascii_string.clone(),
+    }
+}
+
+// 按源/目标配置文件转换像素：8 位和 16 位 RGB(A) 直接转换，其他格式先转为 RGBA；转换失败时返回原图
+fn convert(image: &DynamicImage, source: &ColorProfile, target: &ColorProfile) -> DynamicImage {
+    let options = TransformOptions::default();
+    let result = match image {
+        DynamicImage::ImageRgb8(buffer) => source
+            .create_transform_8bit(Layout::Rgb, target, Layout::Rgb, options)
+            .and_then(|transform| transform_buffer(buffer, |src, dst| transform.transform(src, dst)))
+            .map(DynamicImage::ImageRgb8),
+        DynamicImage::ImageRgba8(buffer) => source
+            .create_transform_8bit(Layout::Rgba, target, Layout::Rgba, options)
+            .and_then(|transform| transform_buffer(buffer, |src, dst| transform.transform(src, dst)))
+            .map(DynamicImage::ImageRgba8),
+        DynamicImage::ImageRgb16(buffer) => source
+            .create_transform_16bit(Layout::Rgb, target, Layout::Rgb, options)
+            .and_then(|transform| transform_buffer(buffer, |src, dst| transform.transform(src, dst)))
+            .map(DynamicImage::ImageRgb16),
+        DynamicImage::ImageRgba16(buffer) => source
+            .create_transform_16bit(Layout::Rgba, target, Layout::Rgba, options)
+            .and_then(|transform| transform_buffer(buffer, |src, dst| transform.transform(src, dst)))
+            .map(DynamicImage::ImageRgba16),
+        _ => return convert(&DynamicImage::ImageRgba16(image.to_rgba16()), source, target),
+    };
+    result.unwrap_or_else(|e| {
+        eprintln!("色彩空间转换失败: {:?}", e);
+        image.clone()
+    })
+}
+
+// 对整张图片的像素数据执行转换，生成同尺寸的新图片
+fn transform_buffer<P: Pixel, E>(
+    buffer: &ImageBuffer<P, Vec<P::Subpixel>>,
+    transform: impl Fn(&[P::Subpixel], &mut [P::Subpixel]) -> Result<(), E>,
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>, E> {
+    let mut target = ImageBuffer::new(buffer.width(), buffer.height());
+    transform(buffer.as_raw(), &mut target)?;
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use moxcms::curve_from_gamma;
+
+    #[test]
+    fn srgb_profiles_are_not_converted() {
+        let srgb = ColorProfile::new_srgb().encode().unwrap();
+        assert!(parse(srgb).is_none());
+
+        // 查找表形式的 sRGB 曲线（部分相机写入的配置文件）
+        let mut lut_srgb = ColorProfile::new_srgb();
+        let evaluator = lut_srgb.red_trc.as_ref().unwrap().make_linear_evaluator().unwrap();
+        let lut: Vec<u16> = (0..1024).map(|i| (evaluator.evaluate_value(i as f32 / 1023.0) * 65535.0).round() as u16).collect();
+        lut_srgb.red_trc = Some(ToneReprCurve::Lut(lut.clone()));
+        lut_srgb.green_trc = Some(ToneReprCurve::Lut(lut.clone()));
+        lut_srgb.blue_trc = Some(ToneReprCurve::Lut(lut));
+        assert!(is_srgb(&lut_srgb));
+    }
+
+    #[test]
+    fn srgb_primaries_with_other_curves_are_converted() {
+        for gamma in [1.0, 2.2] {
+            let mut profile = ColorProfile::new_srgb();
+            profile.red_trc = Some(curve_from_gamma(gamma));
+            profile.green_trc = Some(curve_from_gamma(gamma));
+            profile.blue_trc = Some(curve_from_gamma(gamma));
+            assert!(!is_srgb(&profile), "gamma {}", gamma);
+        }
+        assert!(parse(ColorProfile::new_display_p3().encode().unwrap()).is_some());
+    }
+}
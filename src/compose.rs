@@ -0,0 +1,440 @@
+// ========== 画框合成用的像素操作 ==========
+// 抗锯齿圆角蒙版、阴影蒙版以及按位深混合颜色，
+// 同时支持导出时使用的 RGB8 / RGBA8 / RGB16 / RGBA16 缓冲区。
+
+use std::sync::Arc;
+
+use image::{DynamicImage, ImageBuffer, Luma, Pixel, Primitive};
+use num_traits::{NumCast, ToPrimitive};
+use serde::{Deserialize, Serialize};
+
+/// 覆盖率蒙版（0.0 = 不覆盖，1.0 = 完全覆盖）
+/// 蒙版左上角位于画布的 (x, y)，数据可以按 scale 降采样存储，取值时双线性插值
+pub struct Mask {
+    pub x: i64,
+    pub y: i64,
+    pub width: u32,  // 蒙版在画布上的宽度（像素）
+    pub height: u32, // 蒙版在画布上的高度（像素）
+    scale: u32,      // 降采样倍数，1 表示逐像素存储
+    data_width: u32,
+    data_height: u32,
+    data: Vec<f32>,
+}
+
+impl Mask {
+    /// 从逐像素的覆盖率数据创建蒙版
+    pub fn from_coverage(x: i64, y: i64, width: u32, height: u32, data: Vec<f32>) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            scale: 1,
+            data_width: width,
+            data_height: height,
+            data,
+        }
+    }
+
+    /// 画布像素 (px, py) 处的覆盖率
+    fn sample(&self, px: i64, py: i64) -> f32 {
+        let lx = px - self.x;
+        let ly = py - self.y;
+        if lx < 0 || ly < 0 || lx >= self.width as i64 || ly >= self.height as i64 {
+            return 0.0;
+        }
+        if self.scale == 1 {
+            return self.data[(ly as u32 * self.data_width + lx as u32) as usize];
+        }
+
+        // 降采样数据：按像素中心做双线性插值
+        let fx = ((lx as f32 + 0.5) / self.scale as f32 - 0.5).max(0.0);
+        let fy = ((ly as f32 + 0.5) / self.scale as f32 - 0.5).max(0.0);
+        let x0 = (fx as u32).min(self.data_width - 1);
+        let y0 = (fy as u32).min(self.data_height - 1);
+        let x1 = (x0 + 1).min(self.data_width - 1);
+        let y1 = (y0 + 1).min(self.data_height - 1);
+        let tx = fx - x0 as f32;
+        let ty = fy - y0 as f32;
+        let at = |x: u32, y: u32| self.data[(y * self.data_width + x) as usize];
+        let top = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+        let bottom = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+}
+
+/// 圆角矩形在像素 (px, py) 处的覆盖率（按像素中心到圆角的有符号距离做抗锯齿）
+pub fn rounded_rect_coverage(px: f32, py: f32, width: f32, height: f32, radius: f32) -> f32 {
+    let cx = px + 0.5;
+    let cy = py + 0.5;
+    if cx < 0.0 || cy < 0.0 || cx > width || cy > height {
+        return 0.0;
+    }
+    let radius = radius.min(width / 2.0).min(height / 2.0);
+    if radius <= 0.0 {
+        return 1.0;
+    }
+
+    // 只有落在四个角的圆弧区域内才需要计算距离
+    let dx = (radius - cx).max(cx - (width - radius)).max(0.0);
+    let dy = (radius - cy).max(cy - (height - radius)).max(0.0);
+    if dx == 0.0 || dy == 0.0 {
+        return 1.0;
+    }
+    let distance = (dx * dx + dy * dy).sqrt() - radius;
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// 生成圆角矩形的高斯模糊阴影蒙版
+/// @param x, y - 阴影矩形（未模糊前）在画布上的左上角
+/// @param blur - 模糊半径（约为高斯 sigma 的 2 倍）
+pub fn shadow_mask(x: i64, y: i64, width: u32, height: u32, radius: f32, blur: f32) -> Mask {
+    let sigma = (blur / 2.0).max(0.0);
+    let pad = (sigma * 3.0).ceil() as u32;
+
+    // 阴影是低频信号，模糊较大时在缩小的画布上计算，再插值放大，避免处理整幅原图大小的缓冲
+    let scale = ((sigma / 4.0).floor() as u32).max(1);
+    let full_width = width + pad * 2;
+    let full_height = height + pad * 2;
+    let data_width = full_width.div_ceil(scale);
+    let data_height = full_height.div_ceil(scale);
+
+    let s = scale as f32;
+    let small = ImageBuffer::from_fn(data_width, data_height, |dx, dy| {
+        let px = dx as f32 - pad as f32 / s;
+        let py = dy as f32 - pad as f32 / s;
+        Luma([rounded_rect_coverage(px, py, width as f32 / s, height as f32 / s, radius / s)])
+    });
+    let blurred = if sigma > 0.0 {
+        image::imageops::fast_blur(&small, sigma / s)
+    } else {
+        small
+    };
+
+    Mask {
+        x: x - pad as i64,
+        y: y - pad as i64,
+        width: full_width,
+        height: full_height,
+        scale,
+        data_width,
+        data_height,
+        data: blurred.into_raw(),
+    }
+}
+
+/// 生成柱状图（迷你直方图）的蒙版，柱的边缘按覆盖面积抗锯齿
+/// @param x, y, width, height - 图形在画布上的范围（可以是小数像素）
+/// @param heights - 从左到右各柱的高度（0.0 ~ 1.0），等宽排列
+pub fn bars_mask(x: f32, y: f32, width: f32, height: f32, heights: &[f32]) -> Mask {
+    let left = x.floor() as i64;
+    let top = y.floor() as i64;
+    let mask_width = ((x + width).ceil() as i64 - left).max(0) as u32;
+    let mask_height = ((y + height).ceil() as i64 - top).max(0) as u32;
+    let bottom = y + height;
+    let bar_width = width / heights.len().max(1) as f32;
+
+    // 区间 [a0, a1] 与 [b0, b1] 的重叠长度
+    let overlap = |a0: f32, a1: f32, b0: f32, b1: f32| (a1.min(b1) - a0.max(b0)).max(0.0);
+    let mut data = vec![0.0; (mask_width * mask_height) as usize];
+    for column in 0..mask_width {
+        let px = (left + column as i64) as f32;
+        // 像素列横跨的每根柱按重叠宽度累加覆盖率
+        let first = ((px - x) / bar_width).floor().max(0.0) as usize;
+        let last = (((px + 1.0 - x) / bar_width).ceil().max(0.0) as usize).min(heights.len());
+        for (index, &value) in heights.iter().enumerate().take(last).skip(first) {
+            let bar_left = x + index as f32 * bar_width;
+            let horizontal = overlap(px, px + 1.0, bar_left, bar_left + bar_width);
+            let bar_top = bottom - value.clamp(0.0, 1.0) * height;
+            for row in 0..mask_height {
+                let py = (top + row as i64) as f32;
+                data[(row * mask_width + column) as usize] += horizontal * overlap(py, py + 1.0, bar_top, bottom);
+            }
+        }
+    }
+
+    Mask::from_coverage(left, top, mask_width, mask_height, data)
+}
+
+// 对导出用到的几种缓冲区类型分发同一段泛型代码
+macro_rules! with_buffer {
+    ($image:expr, $buf:ident => $body:expr) => {
+        match $image {
+            DynamicImage::ImageRgb8($buf) => $body,
+            DynamicImage::ImageRgba8($buf) => $body,
+            DynamicImage::ImageRgb16($buf) => $body,
+            DynamicImage::ImageRgba16($buf) => $body,
+            _ => eprintln!("不支持的像素格式，跳过合成"),
+        }
+    };
+}
+
+/// 按覆盖率把纯色混合进一个像素（颜色为 0.0..=1.0 的非预乘 RGB）
+/// 带透明通道的缓冲区按非预乘的 over 运算混合，透明背景上的文字边缘不会发黑
+pub fn blend_pixel<P: Pixel>(pixel: &mut P, color: [f32; 3], alpha: f32) {
+    let max = P::Subpixel::DEFAULT_MAX_VALUE.to_f32().unwrap_or(1.0);
+    let channels = pixel.channels_mut();
+    let to_subpixel = |value: f32, fallback| NumCast::from(value.round().clamp(0.0, max)).unwrap_or(fallback);
+
+    if P::HAS_ALPHA && channels.len() == 4 {
+        let dst_alpha = channels[3].to_f32().unwrap_or(max) / max;
+        let out_alpha = alpha + dst_alpha * (1.0 - alpha);
+        if out_alpha <= 0.0 {
+            return;
+        }
+        for i in 0..3 {
+            let current = channels[i].to_f32().unwrap_or(0.0);
+            let mixed = (color[i] * max * alpha + current * dst_alpha * (1.0 - alpha)) / out_alpha;
+            channels[i] = to_subpixel(mixed, channels[i]);
+        }
+        channels[3] = to_subpixel(out_alpha * max, channels[3]);
+    } else {
+        for (i, value) in channels.iter_mut().enumerate().take(3) {
+            let current = value.to_f32().unwrap_or(0.0);
+            let mixed = current + (color[i] * max - current) * alpha;
+            *value = to_subpixel(mixed, *value);
+        }
+    }
+}
+
+/// 按蒙版把纯色混合到图片上
+pub fn fill_mask(image: &mut DynamicImage, mask: &Mask, color: [f32; 3], opacity: f32) {
+    with_buffer!(image, buf => fill_mask_buffer(buf, mask, color, opacity))
+}
+
+fn fill_mask_buffer<P: Pixel>(
+    buf: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+    mask: &Mask,
+    color: [f32; 3],
+    opacity: f32,
+) {
+    let x_start = mask.x.max(0);
+    let y_start = mask.y.max(0);
+    let x_end = (mask.x + mask.width as i64).min(buf.width() as i64);
+    let y_end = (mask.y + mask.height as i64).min(buf.height() as i64);
+    for py in y_start..y_end {
+        for px in x_start..x_end {
+            let alpha = mask.sample(px, py) * opacity;
+            if alpha > 0.0 {
+                blend_pixel(buf.get_pixel_mut(px as u32, py as u32), color, alpha.min(1.0));
+            }
+        }
+    }
+}
+
+/// 用纯色填充矩形区域（超出画布的部分自动裁剪）
+pub fn fill_rect(image: &mut DynamicImage, x: i64, y: i64, width: u32, height: u32, color: [f32; 3]) {
+    with_buffer!(image, buf => {
+        let x_end = (x + width as i64).min(buf.width() as i64);
+        let y_end = (y + height as i64).min(buf.height() as i64);
+        for py in y.max(0)..y_end {
+            for px in x.max(0)..x_end {
+                blend_pixel(buf.get_pixel_mut(px as u32, py as u32), color, 1.0);
+            }
+        }
+    })
+}
+
+/// 把照片以圆角的形式叠加到背景上，圆角边缘抗锯齿
+pub fn overlay_rounded(bg: &mut DynamicImage, photo: &DynamicImage, x: u32, y: u32, radius: f32) {
+    let (width, height) = (photo.width(), photo.height());
+    let radius = radius.min(width.min(height) as f32 / 2.0).max(0.0);
+    let corner = radius.ceil() as u32;
+
+    // 先保存四个角下方的背景像素，叠加后再按覆盖率混合回去
+    let origins = [
+        (x, y),
+        (x + width - corner, y),
+        (x, y + height - corner),
+        (x + width - corner, y + height - corner),
+    ];
+    let saved: Vec<DynamicImage> = if corner > 0 {
+        origins.iter().map(|&(cx, cy)| bg.crop_imm(cx, cy, corner, corner)).collect()
+    } else {
+        Vec::new()
+    };
+
+    image::imageops::overlay(bg, photo, x as i64, y as i64);
+
+    for (&origin, under) in origins.iter().zip(saved.iter()) {
+        let rect = (x, y, width, height);
+        match (&mut *bg, under) {
+            (DynamicImage::ImageRgb8(dst), DynamicImage::ImageRgb8(src)) => restore_corner(dst, src, origin, rect, radius),
+            (DynamicImage::ImageRgba8(dst), DynamicImage::ImageRgba8(src)) => restore_corner(dst, src, origin, rect, radius),
+            (DynamicImage::ImageRgb16(dst), DynamicImage::ImageRgb16(src)) => restore_corner(dst, src, origin, rect, radius),
+            (DynamicImage::ImageRgba16(dst), DynamicImage::ImageRgba16(src)) => restore_corner(dst, src, origin, rect, radius),
+            _ => eprintln!("不支持的像素格式，跳过圆角处理"),
+        }
+    }
+}
+
+fn restore_corner<P: Pixel>(
+    dst: &mut ImageBuffer<P, Vec<P::Subpixel>>,
+    under: &ImageBuffer<P, Vec<P::Subpixel>>,
+    origin: (u32, u32),
+    rect: (u32, u32, u32, u32),
+    radius: f32,
+) {
+    let (rx, ry, width, height) = rect;
+    for (u, v, under_pixel) in under.enumerate_pixels() {
+        let (gx, gy) = (origin.0 + u, origin.1 + v);
+        let coverage = rounded_rect_coverage(
+            (gx - rx) as f32,
+            (gy - ry) as f32,
+            width as f32,
+            height as f32,
+            radius,
+        );
+        if coverage >= 1.0 {
+            continue;
+        }
+        let pixel = dst.get_pixel_mut(gx, gy);
+        for (value, &below) in pixel.channels_mut().iter_mut().zip(under_pixel.channels()) {
+            let over = value.to_f32().unwrap_or(0.0);
+            let below = below.to_f32().unwrap_or(0.0);
+            let mixed = below + (over - below) * coverage;
+            *value = NumCast::from(mixed.round()).unwrap_or(*value);
+        }
+    }
+}
+
+// ========== 背景填充 ==========
+
+/// 纹理背景的铺放方式
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TextureFit {
+    Tile,    // 平铺（保持纹理原始像素大小）
+    Stretch, // 拉伸铺满整个画布
+}
+
+/// 背景填充方式，颜色均为非预乘 RGBA8（纹理共享引用，可发送到后台线程合成）
+#[derive(Clone)]
+pub enum BackgroundFill {
+    Solid([u8; 4]),
+    LinearGradient { from: [u8; 4], to: [u8; 4], angle_degrees: f32 },
+    RadialGradient { from: [u8; 4], to: [u8; 4] }, // 从画布中心到角落
+    Texture { image: Arc<image::RgbaImage>, fit: TextureFit },
+}
+
+impl BackgroundFill {
+    /// 画布 (x, y) 处的颜色，返回 0.0..=1.0 的 RGBA
+    /// @param width, height - 整个画布的尺寸（预览时传入原图分辨率下的画布尺寸，坐标也按原图换算）
+    pub fn sample(&self, x: f32, y: f32, width: f32, height: f32) -> [f32; 4] {
+        match self {
+            BackgroundFill::Solid(color) => normalize(*color),
+            BackgroundFill::LinearGradient { from, to, angle_degrees } => {
+                // 0° 为从左到右，90° 为从上到下；投影到渐变方向上求插值位置
+                let (sin, cos) = angle_degrees.to_radians().sin_cos();
+                let half_extent = (width * cos.abs() + height * sin.abs()) / 2.0;
+                let projected = (x - width / 2.0) * cos + (y - height / 2.0) * sin;
+                let t = if half_extent > 0.0 { projected / half_extent / 2.0 + 0.5 } else { 0.0 };
+                mix(normalize(*from), normalize(*to), t)
+            }
+            BackgroundFill::RadialGradient { from, to } => {
+                let dx = x - width / 2.0;
+                let dy = y - height / 2.0;
+                let max_distance = (width * width + height * height).sqrt() / 2.0;
+                let t = if max_distance > 0.0 { (dx * dx + dy * dy).sqrt() / max_distance } else { 0.0 };
+                mix(normalize(*from), normalize(*to), t)
+            }
+            BackgroundFill::Texture { image, fit } => {
+                let (tw, th) = (image.width() as f32, image.height() as f32);
+                if tw == 0.0 || th == 0.0 {
+                    return [1.0, 1.0, 1.0, 1.0];
+                }
+                let (u, v) = match fit {
+                    TextureFit::Tile => (x.rem_euclid(tw), y.rem_euclid(th)),
+                    TextureFit::Stretch => (x / width * tw, y / height * th),
+                };
+                sample_bilinear(image, u, v, *fit == TextureFit::Tile)
+            }
+        }
+    }
+}
+
+fn normalize(color: [u8; 4]) -> [f32; 4] {
+    color.map(|c| c as f32 / 255.0)
+}
+
+fn mix(from: [f32; 4], to: [f32; 4], t: f32) -> [f32; 4] {
+    let t = t.clamp(0.0, 1.0);
+    [0, 1, 2, 3].map(|i| from[i] + (to[i] - from[i]) * t)
+}
+
+// 双线性采样纹理，平铺时边缘环绕取值
+fn sample_bilinear(image: &image::RgbaImage, u: f32, v: f32, wrap: bool) -> [f32; 4] {
+    let (w, h) = (image.width() as i64, image.height() as i64);
+    let fx = u - 0.5;
+    let fy = v - 0.5;
+    let x0 = fx.floor() as i64;
+    let y0 = fy.floor() as i64;
+    let tx = fx - x0 as f32;
+    let ty = fy - y0 as f32;
+    let fetch = |x: i64, y: i64| {
+        let (x, y) = if wrap {
+            (x.rem_euclid(w), y.rem_euclid(h))
+        } else {
+            (x.clamp(0, w - 1), y.clamp(0, h - 1))
+        };
+        normalize(image.get_pixel(x as u32, y as u32).0)
+    };
+    let top = mix(fetch(x0, y0), fetch(x0 + 1, y0), tx);
+    let bottom = mix(fetch(x0, y0 + 1), fetch(x0 + 1, y0 + 1), tx);
+    mix(top, bottom, ty)
+}
+
+/// 按位深创建背景：与原图格式一致（RGB8 / RGBA8 / RGBA16），其它格式使用 RGBA8
+/// @param scale - 画布相对原图分辨率的缩放比例（导出为 1.0），采样坐标换算回原图分辨率，平铺纹理随之缩放
+pub fn create_background(like: &DynamicImage, width: u32, height: u32, fill: &BackgroundFill, scale: f32) -> DynamicImage {
+    let (w, h) = (width as f32 / scale, height as f32 / scale);
+    let at = |x: u32, y: u32| fill.sample((x as f32 + 0.5) / scale, (y as f32 + 0.5) / scale, w, h);
+    let to_u8 = |c: f32| (c * 255.0).round().clamp(0.0, 255.0) as u8;
+    let to_u16 = |c: f32| (c * 65535.0).round().clamp(0.0, 65535.0) as u16;
+    match like {
+        DynamicImage::ImageRgb8(_) => DynamicImage::ImageRgb8(ImageBuffer::from_fn(width, height, |x, y| {
+            let c = at(x, y);
+            image::Rgb([to_u8(c[0]), to_u8(c[1]), to_u8(c[2])])
+        })),
+        DynamicImage::ImageRgba16(_) => DynamicImage::ImageRgba16(ImageBuffer::from_fn(width, height, |x, y| {
+            let c = at(x, y);
+            image::Rgba([to_u16(c[0]), to_u16(c[1]), to_u16(c[2]), to_u16(c[3])])
+        })),
+        _ => DynamicImage::ImageRgba8(ImageBuffer::from_fn(width, height, |x, y| {
+            let c = at(x, y);
+            image::Rgba([to_u8(c[0]), to_u8(c[1]), to_u8(c[2]), to_u8(c[3])])
+        })),
+    }
+}
+
+/// 提取照片的主色：在缩小的副本上按 4 位精度分桶，取像素最多的桶的平均色
+pub fn dominant_color(image: &DynamicImage) -> [u8; 3] {
+    let small = image.thumbnail(64, 64).to_rgb8();
+    let mut buckets: std::collections::HashMap<u16, (u32, [u32; 3])> = std::collections::HashMap::new();
+    for pixel in small.pixels() {
+        let [r, g, b] = pixel.0;
+        let key = ((r as u16 >> 4) << 8) | ((g as u16 >> 4) << 4) | (b as u16 >> 4);
+        let entry = buckets.entry(key).or_insert((0, [0; 3]));
+        entry.0 += 1;
+        entry.1[0] += r as u32;
+        entry.1[1] += g as u32;
+        entry.1[2] += b as u32;
+    }
+    buckets
+        .values()
+        .max_by_key(|(count, _)| *count)
+        .map(|(count, sum)| sum.map(|c| (c / count) as u8))
+        .unwrap_or([255, 255, 255])
+}
+
+/// 纹理的平均颜色，用于决定文字颜色
+pub fn average_color(image: &image::RgbaImage) -> [u8; 3] {
+    let small = image::imageops::thumbnail(image, 32, 32);
+    let count = (small.width() * small.height()).max(1) as u64;
+    let mut sum = [0u64; 3];
+    for pixel in small.pixels() {
+        for (s, c) in sum.iter_mut().zip(pixel.0) {
+            *s += c as u64;
+        }
+    }
+    sum.map(|c| (c / count) as u8)
+}
@@ -0,0 +1,177 @@
+// ========== 文件夹缩略图胶片条 ==========
+// 列出当前图片所在文件夹中的所有图片，在后台线程逐张生成缩略图：
+// 优先使用 EXIF 中内嵌的缩略图（不必解码整张照片），没有时再解码原图并缩小，均按 EXIF 方向摆正。
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+
+use eframe::egui::{self, ColorImage, TextureHandle, TextureOptions};
+use exif::{In, Reader, Tag};
+use image::metadata::Orientation;
+use image::DynamicImage;
+
+use crate::{color, loader};
+
+// 缩略图长边像素
+pub const THUMBNAIL_SIZE: u32 = 160;
+
+// 能够打开的图片扩展名
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// 胶片条中的一张图片
+pub struct FilmstripEntry {
+    pub path: String,
+    pub name: String,
+    pub texture: Option<TextureHandle>, // 缩略图生成前为 None
+    pub failed: bool,                   // 缩略图生成失败
+}
+
+/// 当前文件夹的缩略图列表
+#[derive(Default)]
+pub struct Filmstrip {
+    folder: Option<PathBuf>,
+    pub entries: Vec<FilmstripEntry>,
+    receiver: Option<Receiver<(usize, Option<ColorImage>)>>, // 后台线程发回的 (序号, 缩略图)
+    pub scroll_to_current: bool, // 下一帧把当前图片滚动到可见位置
+}
+
+impl Filmstrip {
+    /// 切换到图片所在的文件夹，重新列出图片并在后台生成缩略图（文件夹未变化时不做任何事）
+    pub fn open_folder(&mut self, folder: &Path) {
+        if self.folder.as_deref() == Some(folder) {
+            return;
+        }
+        self.folder = Some(folder.to_path_buf());
+        self.scroll_to_current = true;
+        self.entries = list_images(folder)
+            .into_iter()
+            .map(|path| FilmstripEntry {
+                name: path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(),
+                path: path.display().to_string(),
+                texture: None,
+                failed: false,
+            })
+            .collect();
+
+        let paths: Vec<String> = self.entries.iter().map(|entry| entry.path.clone()).collect();
+        let (sender, receiver) = mpsc::channel();
+        // 切换文件夹后旧的接收端被丢弃，旧线程发送失败时自行退出
+        std::thread::spawn(move || {
+            for (index, path) in paths.iter().enumerate() {
+                let thumbnail = match load_thumbnail(Path::new(path)) {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        eprintln!("无法生成缩略图 {}: {}", path, e);
+                        None
+                    }
+                };
+                if sender.send((index, thumbnail)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.receiver = Some(receiver);
+    }
+
+    /// 接收后台线程生成的缩略图并上传为纹理，返回是否仍在生成
+    pub fn poll(&mut self, ctx: &egui::Context) -> bool {
+        let Some(receiver) = &self.receiver else {
+            return false;
+        };
+        loop {
+            match receiver.try_recv() {
+                Ok((index, thumbnail)) => {
+                    let Some(entry) = self.entries.get_mut(index) else {
+                        continue;
+                    };
+                    match thumbnail {
+                        Some(image) => {
+                            let name = format!("thumbnail_{}", entry.path);
+                            entry.texture = Some(ctx.load_texture(name, image, TextureOptions::LINEAR));
+                        }
+                        None => entry.failed = true,
+                    }
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => {
+                    self.receiver = None;
+                    return false;
+                }
+            }
+        }
+    }
+
+    /// 图片在胶片条中的序号
+    pub fn position(&self, path: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.path == path)
+    }
+
+    /// 从当前图片前后移动 offset 张（到头时停在首尾），返回目标图片路径
+    pub fn step(&self, current: &str, offset: isize) -> Option<&str> {
+        let last = self.entries.len().checked_sub(1)?;
+        let target = match self.position(current) {
+            Some(index) => index.saturating_add_signed(offset).min(last),
+            None => 0,
+        };
+        Some(self.entries[target].path.as_str())
+    }
+}
+
+/// 是否为能够打开的图片文件（按扩展名判断）
+pub fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// 列出文件夹中的图片，按文件名排序
+pub fn list_images(folder: &Path) -> Vec<PathBuf> {
+    let Ok(read_dir) = std::fs::read_dir(folder) else {
+        eprintln!("无法读取文件夹: {}", folder.display());
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = read_dir
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && is_supported_image(path))
+        .collect();
+    paths.sort_by_key(|path| path.file_name().map(|n| n.to_string_lossy().to_lowercase()));
+    paths
+}
+
+// 生成缩略图：优先使用 EXIF 内嵌缩略图，否则解码原图后缩小；内嵌缩略图与原图使用同一个配置文件
+fn load_thumbnail(path: &Path) -> Result<ColorImage, String> {
+    let (image, icc) = match exif_thumbnail(path) {
+        Some(image) => (image, loader::read_icc_profile(path).and_then(color::parse)),
+        None => {
+            let decoded = loader::decode_oriented(path, None)?;
+            (decoded.image, decoded.icc.and_then(color::parse))
+        }
+    };
+    // 照片内嵌非 sRGB 配置文件时把缩略图转换为 sRGB
+    let small = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let small = match icc {
+        Some(icc) => icc.to_srgb(&small).to_rgba8(),
+        None => small.to_rgba8(),
+    };
+    Ok(ColorImage::from_rgba_unmultiplied([small.width() as usize, small.height() as usize], &small))
+}
+
+// 读取 EXIF 第二个 IFD 中内嵌的 JPEG 缩略图并按主图的方向摆正，没有时返回 None
+fn exif_thumbnail(path: &Path) -> Option<DynamicImage> {
+    let file = File::open(path).ok()?;
+    let exif = Reader::new().read_from_container(&mut BufReader::new(file)).ok()?;
+    let offset = exif.get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?.value.get_uint(0)? as usize;
+    let length = exif.get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?.value.get_uint(0)? as usize;
+    let data = exif.buf().get(offset..offset.checked_add(length)?)?;
+    let mut image = image::load_from_memory_with_format(data, image::ImageFormat::Jpeg).ok()?;
+    let orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| Orientation::from_exif(value as u8))
+        .unwrap_or(Orientation::NoTransforms);
+    image.apply_orientation(orientation);
+    Some(image)
+}
@@ -0,0 +1,450 @@
+// ========== 字体库 ==========
+// 扫描系统字体目录（可选内置开源字体），解析字体族名和字重，
+// 为界面和画框文字按「字体族 + 字重」组装逐字回退链，内置字体排在回退链最后。
+// 扫描时只读取字体文件的表目录和元数据用到的几个表（name、OS/2、cmap 等），不读字形数据；
+// 完整的字体数据在第一次使用时才读入内存。
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+use serde::{Deserialize, Serialize};
+use ttf_parser::{name_id, Face, RawFaceTables};
+
+use crate::text_render::FontSource;
+
+// 内置的开源中英文字体（启用 bundled-font 特性时编译进程序，见 assets/fonts/README.md）：
+// 系统中没有中文字体时，界面和画框文字仍有可用的回退字体
+#[cfg(feature = "bundled-font")]
+const BUNDLED_FONT: &[u8] = include_bytes!("../assets/fonts/NotoSansSC-Regular.otf");
+
+// 常见中文字体，按优先级排列，作为缺字时的回退字体
+const PREFERRED_CJK_FAMILIES: &[&str] = &[
+    "Microsoft YaHei",
+    "PingFang SC",
+    "Noto Sans SC",
+    "Noto Sans CJK SC",
+    "Source Han Sans SC",
+    "WenQuanYi Micro Hei",
+    "SimSun",
+];
+
+// 常见英文字体，中文回退字体也缺字时使用
+const PREFERRED_LATIN_FAMILIES: &[&str] = &[
+    "Arial",
+    "Helvetica",
+    "Liberation Sans",
+    "DejaVu Sans",
+    "FreeSans",
+];
+
+// 字体目录递归扫描的最大深度，防止符号链接造成死循环
+const MAX_SCAN_DEPTH: usize = 8;
+
+/// 字重
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FontWeight {
+    Light,
+    Regular,
+    Bold,
+}
+
+impl FontWeight {
+    pub const ALL: [FontWeight; 3] = [FontWeight::Light, FontWeight::Regular, FontWeight::Bold];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FontWeight::Light => "细体",
+            FontWeight::Regular => "常规",
+            FontWeight::Bold => "粗体",
+        }
+    }
+
+    // OpenType 的 usWeightClass 数值
+    pub fn value(&self) -> u16 {
+        match self {
+            FontWeight::Light => 300,
+            FontWeight::Regular => 400,
+            FontWeight::Bold => 700,
+        }
+    }
+}
+
+// 字体文件，字体集合（.ttc）中的多个 face 共享同一份数据
+struct FontFile {
+    path: Option<PathBuf>, // None 表示内置字体（数据在创建时就已放入）
+    data: OnceLock<Option<Arc<Vec<u8>>>>,
+}
+
+impl FontFile {
+    // 读取字体数据（只在第一次调用时读文件）
+    fn data(&self) -> Option<Arc<Vec<u8>>> {
+        self.data
+            .get_or_init(|| {
+                let path = self.path.as_ref()?;
+                match std::fs::read(path) {
+                    Ok(data) => Some(Arc::new(data)),
+                    Err(e) => {
+                        eprintln!("无法读取字体 {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+}
+
+/// 字体库中的一个字形（字体文件中的一个 face）
+pub struct FontFace {
+    pub family: String,
+    pub style: String,  // 样式名，如 Regular、Bold、Light
+    pub weight: u16,
+    pub index: u32,     // 在字体集合中的序号，单个字体文件为 0
+    pub has_cjk: bool,  // 是否包含中文字形
+    pub upright: bool,  // 非斜体、标准宽度，按字重匹配时优先使用
+    file: Arc<FontFile>,
+}
+
+impl FontFace {
+    pub fn source(&self) -> Option<FontSource> {
+        self.file.data().map(|data| FontSource::new(data, self.index))
+    }
+
+    pub fn is_bundled(&self) -> bool {
+        self.file.path.is_none()
+    }
+}
+
+/// 已加载的字体集合
+#[derive(Default)]
+pub struct FontLibrary {
+    faces: Vec<FontFace>,
+}
+
+impl FontLibrary {
+    /// 加载内置字体并扫描当前系统的字体目录
+    pub fn load_system() -> Self {
+        let mut library = Self::default();
+
+        #[cfg(feature = "bundled-font")]
+        library.add_bundled(BUNDLED_FONT);
+
+        let mut files = Vec::new();
+        for dir in font_directories() {
+            collect_font_files(&dir, 0, &mut files);
+        }
+        files.sort();
+        files.dedup();
+        for path in files {
+            library.add_file(path);
+        }
+
+        if library.faces.is_empty() {
+            eprintln!("未找到任何可用字体");
+        } else if library.cjk_fallback_family().is_none() {
+            eprintln!("未找到中文字体，中文无法显示（可启用 bundled-font 特性内置开源中文字体）");
+        }
+        library
+    }
+
+    // 内置字体已在内存中，直接解析整个字体
+    #[cfg(feature = "bundled-font")]
+    fn add_bundled(&mut self, data: &'static [u8]) {
+        let file = Arc::new(FontFile { path: None, data: OnceLock::new() });
+        let _ = file.data.set(Some(Arc::new(data.to_vec())));
+        let count = ttf_parser::fonts_in_collection(data).unwrap_or(1);
+        let mut added = false;
+        for index in 0..count {
+            if let Ok(face) = Face::parse(data, index) {
+                self.push_face(&face, index, &file, "内置字体");
+                added = true;
+            }
+        }
+        if !added {
+            eprintln!("无法解析内置字体");
+        }
+    }
+
+    /// 读取字体文件的元数据并加入字体库（字体集合中可能有多个 face），返回是否至少解析成功一个
+    pub fn add_file(&mut self, path: PathBuf) -> bool {
+        let Some(faces) = read_metadata_tables(&path) else {
+            eprintln!("无法解析字体: {}", path.display());
+            return false;
+        };
+        let fallback_name = path.file_stem().and_then(|s| s.to_str()).unwrap_or("未知字体").to_string();
+        let file = Arc::new(FontFile { path: Some(path.clone()), data: OnceLock::new() });
+        let mut added = false;
+        for (index, tables) in faces.iter().enumerate() {
+            let Some(Ok(face)) = tables.as_ref().map(|tables| Face::from_raw_tables(tables.raw())) else {
+                continue;
+            };
+            self.push_face(&face, index as u32, &file, &fallback_name);
+            added = true;
+        }
+        if !added {
+            eprintln!("无法解析字体: {}", path.display());
+        }
+        added
+    }
+
+    // 记录一个 face 的元数据
+    fn push_face(&mut self, face: &Face, index: u32, file: &Arc<FontFile>, fallback_name: &str) {
+        let weight = face.weight().to_number();
+        self.faces.push(FontFace {
+            family: family_name(face).unwrap_or_else(|| fallback_name.to_string()),
+            style: style_name(face).unwrap_or_else(|| weight.to_string()),
+            weight,
+            index,
+            has_cjk: face.glyph_index('中').is_some(),
+            upright: !face.is_italic() && !face.is_oblique() && face.width() == ttf_parser::Width::Normal,
+            file: file.clone(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.faces.is_empty()
+    }
+
+    pub fn contains_family(&self, family: &str) -> bool {
+        self.faces.iter().any(|face| face.family == family)
+    }
+
+    /// 所有字体族名（按名称排序并去重）
+    pub fn families(&self) -> Vec<String> {
+        let mut families: Vec<String> = self.faces.iter().map(|face| face.family.clone()).collect();
+        families.sort_by_key(|family| family.to_lowercase());
+        families.dedup();
+        families
+    }
+
+    /// 字体族中所有 face 的样式名（按字重从细到粗排序）
+    pub fn styles(&self, family: &str) -> Vec<String> {
+        let mut faces: Vec<&FontFace> = self.faces.iter().filter(|face| face.family == family).collect();
+        faces.sort_by_key(|face| face.weight);
+        let mut styles: Vec<String> = Vec::new();
+        for face in faces {
+            if !styles.contains(&face.style) {
+                styles.push(face.style.clone());
+            }
+        }
+        styles
+    }
+
+    /// 缺字时使用的中文回退字体族：常见中文字体优先，其次是任意含中文的系统字体，最后是内置字体
+    pub fn cjk_fallback_family(&self) -> Option<&str> {
+        PREFERRED_CJK_FAMILIES
+            .iter()
+            .find_map(|name| self.faces.iter().find(|face| face.family == *name && face.has_cjk))
+            .or_else(|| self.faces.iter().find(|face| face.has_cjk && !face.is_bundled()))
+            .or_else(|| self.bundled_face())
+            .map(|face| face.family.as_str())
+    }
+
+    // 内置字体（未启用 bundled-font 特性时为 None）
+    fn bundled_face(&self) -> Option<&FontFace> {
+        self.faces.iter().find(|face| face.is_bundled())
+    }
+
+    /// 组装字体回退链：指定字体族中的指定样式（未指定样式时取字重最接近的字形）优先，
+    /// 然后是中文、英文回退字体，最后是内置字体，用于补全缺字
+    pub fn chain(&self, family: Option<&str>, style: Option<&str>, weight: FontWeight) -> Vec<FontSource> {
+        // 最后是常见英文字体，都没有时退回字体库中的第一个字体族，保证至少有一个字体可用
+        let latin_family = PREFERRED_LATIN_FAMILIES
+            .iter()
+            .copied()
+            .find(|name| self.contains_family(name))
+            .or_else(|| self.faces.first().map(|face| face.family.as_str()));
+        let bundled_family = self.bundled_face().map(|face| face.family.as_str());
+        let mut families: Vec<&str> = Vec::new();
+        for candidate in [family, self.cjk_fallback_family(), latin_family, bundled_family].into_iter().flatten() {
+            if !families.contains(&candidate) {
+                families.push(candidate);
+            }
+        }
+        let styled_face = family
+            .zip(style)
+            .and_then(|(family, style)| self.faces.iter().find(|face| face.family == family && face.style == style));
+        families
+            .iter()
+            .enumerate()
+            .filter_map(|(i, family)| match styled_face {
+                Some(face) if i == 0 => Some(face),
+                _ => self.closest_face(family, weight.value()),
+            })
+            .filter_map(|face| face.source())
+            .collect()
+    }
+
+    fn closest_face(&self, family: &str, weight: u16) -> Option<&FontFace> {
+        self.faces
+            .iter()
+            .filter(|face| face.family == family)
+            // 优先正体、标准宽度；字重距离相同时，细字重偏向更细的字形，其余偏向更粗的字形（与 CSS 字重匹配规则一致）
+            .min_by_key(|face| {
+                let distance = (face.weight as i32 - weight as i32).abs();
+                let tie_break = if weight < 400 { face.weight as i32 } else { -(face.weight as i32) };
+                (!face.upright, distance, tie_break)
+            })
+    }
+}
+
+// 读取字体族名：优先使用排版族名（不含字重后缀）
+fn family_name(face: &Face) -> Option<String> {
+    localized_name(face, &[name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY])
+}
+
+// 读取样式名（如 Regular、Bold、Light）
+fn style_name(face: &Face) -> Option<String> {
+    localized_name(face, &[name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY])
+}
+
+// 按顺序查找 name 表中的名称，优先英文名称，保证跨语言系统一致
+fn localized_name(face: &Face, ids: &[u16]) -> Option<String> {
+    let names: Vec<_> = face.names().into_iter().filter(|name| name.is_unicode()).collect();
+    for id in ids {
+        let candidates: Vec<_> = names.iter().filter(|name| name.name_id == *id).collect();
+        let english = candidates.iter().find(|name| name.language_id == 0x0409 || name.language_id == 0);
+        if let Some(name) = english.or(candidates.first()).and_then(|name| name.to_string()) {
+            return Some(name);
+        }
+    }
+    None
+}
+
+// 各系统的字体目录（系统级 + 用户级），另加程序目录下的 fonts 文件夹
+fn font_directories() -> Vec<PathBuf> {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "windows") {
+        let windir = std::env::var_os("WINDIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("C:\\Windows"));
+        dirs.push(windir.join("Fonts"));
+        if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join("Microsoft\\Windows\\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        dirs.extend(home.map(|home| home.join("Library/Fonts")));
+    } else {
+        // Linux 及其它类 Unix 系统（fontconfig 的默认目录）
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+        dirs.push(PathBuf::from("/usr/local/share/fonts"));
+        match std::env::var_os("XDG_DATA_HOME") {
+            Some(data_home) => dirs.push(PathBuf::from(data_home).join("fonts")),
+            None => dirs.extend(home.as_ref().map(|home| home.join(".local/share/fonts"))),
+        }
+        dirs.extend(home.map(|home| home.join(".fonts")));
+    }
+    dirs.push(PathBuf::from("fonts"));
+    dirs
+}
+
+// 解析元数据需要的表：head/hhea/maxp 是必需表，name 和 OS/2 提供族名、样式和字重，
+// cmap 用于判断是否包含中文字形，post 提供斜体角度
+struct MetadataTables {
+    head: Vec<u8>,
+    hhea: Vec<u8>,
+    maxp: Vec<u8>,
+    name: Option<Vec<u8>>,
+    os2: Option<Vec<u8>>,
+    cmap: Option<Vec<u8>>,
+    post: Option<Vec<u8>>,
+}
+
+impl MetadataTables {
+    fn raw(&self) -> RawFaceTables<'_> {
+        RawFaceTables {
+            head: &self.head,
+            hhea: &self.hhea,
+            maxp: &self.maxp,
+            name: self.name.as_deref(),
+            os2: self.os2.as_deref(),
+            cmap: self.cmap.as_deref(),
+            post: self.post.as_deref(),
+            ..Default::default()
+        }
+    }
+}
+
+// 读取字体文件（字体集合中的每个 face）的元数据表，缺少必需表的 face 为 None；
+// 只读取文件头、表目录和这几个表，不读取字形数据
+fn read_metadata_tables(path: &Path) -> Option<Vec<Option<MetadataTables>>> {
+    let mut file = File::open(path).ok()?;
+    let file_len = file.metadata().ok()?.len();
+    let mut read_at = |offset: u64, len: u64| -> Option<Vec<u8>> {
+        if offset.checked_add(len)? > file_len {
+            return None;
+        }
+        let mut buffer = vec![0u8; len as usize];
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        file.read_exact(&mut buffer).ok()?;
+        Some(buffer)
+    };
+    let u16_at = |data: &[u8], at: usize| u16::from_be_bytes([data[at], data[at + 1]]);
+    let u32_at = |data: &[u8], at: usize| u32::from_be_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]]);
+
+    // 字体集合（ttcf）的文件头列出每个 face 的表目录位置，单个字体的表目录在文件开头
+    let header = read_at(0, 12)?;
+    let directories: Vec<u64> = if &header[0..4] == b"ttcf" {
+        let count = u32_at(&header, 8) as u64;
+        let offsets = read_at(12, count * 4)?;
+        offsets.chunks_exact(4).map(|offset| u32_at(offset, 0) as u64).collect()
+    } else {
+        vec![0]
+    };
+
+    let mut faces = Vec::new();
+    for directory in directories {
+        let count = u16_at(&read_at(directory, 12)?, 4) as u64;
+        let records = read_at(directory + 12, count * 16)?;
+        let mut table = |tag: &[u8]| {
+            let record = records.chunks_exact(16).find(|record| &record[0..4] == tag)?;
+            read_at(u32_at(record, 8) as u64, u32_at(record, 12) as u64)
+        };
+        let (head, hhea, maxp) = (table(b"head"), table(b"hhea"), table(b"maxp"));
+        let tables = head.zip(hhea).zip(maxp).map(|((head, hhea), maxp)| MetadataTables {
+            head,
+            hhea,
+            maxp,
+            name: table(b"name"),
+            os2: table(b"OS/2"),
+            cmap: table(b"cmap"),
+            post: table(b"post"),
+        });
+        faces.push(tables);
+    }
+    Some(faces)
+}
+
+// 递归收集目录中的字体文件
+fn collect_font_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    if depth > MAX_SCAN_DEPTH {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_font_files(&path, depth + 1, files);
+        } else if is_font_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+fn is_font_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| matches!(ext.to_lowercase().as_str(), "ttf" | "otf" | "ttc" | "otc"))
+        .unwrap_or(false)
+}
+
+/// 需要持久化的字体选择（界面字体与导出字体分开保存）
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct FontSettings {
+    pub ui_font: Option<String>,     // 界面字体族，None 时使用默认中文字体
+    pub export_font: Option<String>, // 画框文字的默认字体族，None 时使用默认中文字体
+}
@@ -0,0 +1,446 @@
+// ========== 几何调整：旋转、翻转、拉直和裁剪 ==========
+// 调整以参数形式保存在文档中，不修改原图：预览时换算成原图纹理坐标绘制，导出时才处理原图像素。
+// 处理顺序：顺时针旋转 90° 的整数倍 → 水平翻转 → 拉直（按任意角度旋转后裁掉空白边角）→ 裁剪。
+
+use std::borrow::Cow;
+
+use image::{DynamicImage, ImageBuffer, Pixel};
+use num_traits::{NumCast, ToPrimitive};
+
+// 拉直角度范围（度）
+pub const MAX_STRAIGHTEN: f32 = 45.0;
+// 裁剪框的最小边长（占拉直后图片的比例）
+const MIN_CROP: f32 = 0.02;
+
+/// 裁剪范围，坐标为拉直后图片的比例（0.0 ~ 1.0）
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CropRect {
+    pub x0: f32,
+    pub y0: f32,
+    pub x1: f32,
+    pub y1: f32,
+}
+
+/// 拖动裁剪框的方式
+#[derive(Clone, Copy, PartialEq)]
+pub enum CropHandle {
+    Move, // 拖动框内：平移
+    // 拖动边或角：-1 为左/上边，1 为右/下边，0 为不移动该方向的边
+    Resize { horizontal: i8, vertical: i8 },
+}
+
+impl CropRect {
+    pub const FULL: CropRect = CropRect { x0: 0.0, y0: 0.0, x1: 1.0, y1: 1.0 };
+
+    pub fn width(&self) -> f32 {
+        self.x1 - self.x0
+    }
+
+    pub fn height(&self) -> f32 {
+        self.y1 - self.y0
+    }
+
+    /**
+     * 从拖动开始时的裁剪框出发，按拖动的总位移计算新的裁剪框
+     * @param delta - 拖动的总位移（比例坐标）
+     * @param aspect - 固定比例（比例坐标下的宽 / 高），None 为自由裁剪
+     */
+    pub fn drag(&self, handle: CropHandle, delta: (f32, f32), aspect: Option<f32>) -> CropRect {
+        match handle {
+            CropHandle::Move => {
+                let dx = delta.0.clamp(-self.x0, 1.0 - self.x1);
+                let dy = delta.1.clamp(-self.y0, 1.0 - self.y1);
+                CropRect { x0: self.x0 + dx, y0: self.y0 + dy, x1: self.x1 + dx, y1: self.y1 + dy }
+            }
+            CropHandle::Resize { horizontal, vertical } => {
+                let mut rect = *self;
+                match horizontal {
+                    -1 => rect.x0 = (rect.x0 + delta.0).clamp(0.0, rect.x1 - MIN_CROP),
+                    1 => rect.x1 = (rect.x1 + delta.0).clamp(rect.x0 + MIN_CROP, 1.0),
+                    _ => {}
+                }
+                match vertical {
+                    -1 => rect.y0 = (rect.y0 + delta.1).clamp(0.0, rect.y1 - MIN_CROP),
+                    1 => rect.y1 = (rect.y1 + delta.1).clamp(rect.y0 + MIN_CROP, 1.0),
+                    _ => {}
+                }
+                match aspect {
+                    Some(aspect) => rect.with_aspect(aspect, horizontal, vertical),
+                    None => rect,
+                }
+            }
+        }
+    }
+
+    /**
+     * 把裁剪框调整为固定比例：拖动的边或角跟随光标，对边（或对角）保持不动，
+     * 没有拖动的方向以中心为准；超出图片时整体缩小
+     * @param aspect - 比例坐标下的宽 / 高
+     * @param horizontal, vertical - 拖动的边，含义同 CropHandle::Resize（均为 0 时以中心为准）
+     */
+    pub fn with_aspect(&self, aspect: f32, horizontal: i8, vertical: i8) -> CropRect {
+        let (mut width, mut height) = (self.width(), self.height());
+        match (horizontal != 0, vertical != 0) {
+            (true, false) => height = width / aspect,
+            (false, true) => width = height * aspect,
+            // 拖动角或重新套用比例时取能覆盖当前框的尺寸
+            _ => {
+                if width / height > aspect {
+                    height = width / aspect;
+                } else {
+                    width = height * aspect;
+                }
+            }
+        }
+
+        // 锚点（保持不动的位置）和该方向上可用的最大尺寸
+        let anchor = |side: i8, min: f32, max: f32| match side {
+            -1 => (max, max),
+            1 => (min, 1.0 - min),
+            _ => {
+                let center = (min + max) / 2.0;
+                (center, 2.0 * center.min(1.0 - center))
+            }
+        };
+        let (anchor_x, max_width) = anchor(horizontal, self.x0, self.x1);
+        let (anchor_y, max_height) = anchor(vertical, self.y0, self.y1);
+        let shrink = (max_width / width).min(max_height / height).min(1.0);
+        width *= shrink;
+        height *= shrink;
+
+        let start = |side: i8, anchor: f32, size: f32| match side {
+            -1 => anchor - size,
+            1 => anchor,
+            _ => anchor - size / 2.0,
+        };
+        let x0 = start(horizontal, anchor_x, width);
+        let y0 = start(vertical, anchor_y, height);
+        CropRect { x0, y0, x1: x0 + width, y1: y0 + height }
+    }
+}
+
+/// 几何调整参数
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Geometry {
+    pub quarter_turns: u8,       // 顺时针旋转 90° 的次数（0 ~ 3）
+    pub flipped: bool,           // 旋转后水平翻转（垂直翻转等于水平翻转再旋转 180°）
+    pub straighten: f32,         // 拉直角度（度，正值为顺时针）
+    pub crop: Option<CropRect>,  // 裁剪范围，None 为不裁剪
+}
+
+impl Geometry {
+    /// 是否没有任何调整
+    pub fn is_identity(&self) -> bool {
+        self.quarter_turns.is_multiple_of(4)
+            && !self.flipped
+            && self.straighten == 0.0
+            && self.crop.is_none_or(|crop| crop == CropRect::FULL)
+    }
+
+    /// 去掉裁剪后的参数（裁剪时显示完整画面）
+    pub fn uncropped(&self) -> Geometry {
+        Geometry { crop: None, ..*self }
+    }
+
+    /// 旋转 90°，裁剪框随画面一起旋转
+    pub fn rotate(&mut self, clockwise: bool) {
+        // 翻转后再旋转相当于先反方向旋转再翻转
+        let turn = if clockwise != self.flipped { 1 } else { 3 };
+        self.quarter_turns = (self.quarter_turns + turn) % 4;
+        self.crop = self.crop.map(|c| {
+            if clockwise {
+                CropRect { x0: 1.0 - c.y1, y0: c.x0, x1: 1.0 - c.y0, y1: c.x1 }
+            } else {
+                CropRect { x0: c.y0, y0: 1.0 - c.x1, x1: c.y1, y1: 1.0 - c.x0 }
+            }
+        });
+    }
+
+    /// 水平或垂直翻转，拉直方向和裁剪框随之镜像
+    pub fn flip(&mut self, horizontal: bool) {
+        if !horizontal {
+            self.quarter_turns = (self.quarter_turns + 2) % 4;
+        }
+        self.flipped = !self.flipped;
+        self.straighten = -self.straighten;
+        self.crop = self.crop.map(|c| {
+            if horizontal {
+                CropRect { x0: 1.0 - c.x1, x1: 1.0 - c.x0, ..c }
+            } else {
+                CropRect { y0: 1.0 - c.y1, y1: 1.0 - c.y0, ..c }
+            }
+        });
+    }
+
+    /// 旋转 90° 的整数倍后的尺寸
+    fn oriented_size(&self, width: u32, height: u32) -> (u32, u32) {
+        if self.quarter_turns % 2 == 1 { (height, width) } else { (width, height) }
+    }
+
+    /// 拉直后的尺寸：旋转后画面内保持原比例的最大矩形
+    pub fn straightened_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = self.oriented_size(width, height);
+        if self.straighten == 0.0 {
+            return (width, height);
+        }
+        let (sin, cos) = self.straighten.abs().to_radians().sin_cos();
+        let (w, h) = (width as f32, height as f32);
+        let factor = (w / (w * cos + h * sin)).min(h / (w * sin + h * cos));
+        (((w * factor).round() as u32).max(1), ((h * factor).round() as u32).max(1))
+    }
+
+    /// 裁剪范围对应的像素矩形 (x, y, 宽, 高)，以拉直后的图片为准
+    fn crop_pixels(&self, width: u32, height: u32) -> (u32, u32, u32, u32) {
+        let crop = self.crop.unwrap_or(CropRect::FULL);
+        let x0 = ((crop.x0 * width as f32).round() as u32).min(width - 1);
+        let y0 = ((crop.y0 * height as f32).round() as u32).min(height - 1);
+        let x1 = ((crop.x1 * width as f32).round() as u32).clamp(x0 + 1, width);
+        let y1 = ((crop.y1 * height as f32).round() as u32).clamp(y0 + 1, height);
+        (x0, y0, x1 - x0, y1 - y0)
+    }
+
+    /// 调整后的图片尺寸
+    pub fn output_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (straightened_width, straightened_height) = self.straightened_size(width, height);
+        let (_, _, crop_width, crop_height) = self.crop_pixels(straightened_width, straightened_height);
+        (crop_width, crop_height)
+    }
+
+    /**
+     * 把调整后图片中的点换算为原图中的点（连续坐标，像素 (x, y) 的中心为 (x + 0.5, y + 0.5)）
+     * @param width, height - 原图尺寸
+     */
+    pub fn source_point(&self, width: u32, height: u32, point: (f32, f32)) -> (f32, f32) {
+        let (oriented_width, oriented_height) = self.oriented_size(width, height);
+        let (straightened_width, straightened_height) = self.straightened_size(width, height);
+        let (crop_x, crop_y, _, _) = self.crop_pixels(straightened_width, straightened_height);
+
+        // 裁剪和拉直：以画面中心为原点反向旋转
+        let px = point.0 + crop_x as f32 - straightened_width as f32 / 2.0;
+        let py = point.1 + crop_y as f32 - straightened_height as f32 / 2.0;
+        let (sin, cos) = self.straighten.to_radians().sin_cos();
+        let mut x = oriented_width as f32 / 2.0 + px * cos + py * sin;
+        let mut y = oriented_height as f32 / 2.0 - px * sin + py * cos;
+
+        // 撤销翻转，再逐次撤销 90° 旋转
+        if self.flipped {
+            x = oriented_width as f32 - x;
+        }
+        let (mut current_width, mut current_height) = (oriented_width as f32, oriented_height as f32);
+        for _ in 0..self.quarter_turns % 4 {
+            (x, y) = (y, current_width - x);
+            (current_width, current_height) = (current_height, current_width);
+        }
+        (x, y)
+    }
+
+    /// 对图片像素执行调整（导出时使用原图，保留位深）
+    pub fn apply(&self, image: &DynamicImage) -> DynamicImage {
+        let rotated = match self.quarter_turns % 4 {
+            1 => Cow::Owned(image.rotate90()),
+            2 => Cow::Owned(image.rotate180()),
+            3 => Cow::Owned(image.rotate270()),
+            _ => Cow::Borrowed(image),
+        };
+        let oriented = if self.flipped { Cow::Owned(rotated.fliph()) } else { rotated };
+
+        let (straightened_width, straightened_height) = self.straightened_size(image.width(), image.height());
+        let (crop_x, crop_y, crop_width, crop_height) = self.crop_pixels(straightened_width, straightened_height);
+        if self.straighten == 0.0 {
+            return oriented.crop_imm(crop_x, crop_y, crop_width, crop_height);
+        }
+
+        // 拉直需要重新采样：逐像素反向旋转到旋转后的图片中双线性取值
+        let (sin, cos) = self.straighten.to_radians().sin_cos();
+        let center = (oriented.width() as f32 / 2.0, oriented.height() as f32 / 2.0);
+        let offset = (
+            crop_x as f32 - straightened_width as f32 / 2.0,
+            crop_y as f32 - straightened_height as f32 / 2.0,
+        );
+        let source = |x: u32, y: u32| {
+            let px = x as f32 + 0.5 + offset.0;
+            let py = y as f32 + 0.5 + offset.1;
+            (center.0 + px * cos + py * sin, center.1 - px * sin + py * cos)
+        };
+        resample_image(&oriented, (crop_width, crop_height), source)
+    }
+
+    /**
+     * 只生成调整后图片中的一块区域（放大镜使用）：按 source_point 直接从原图取值，不处理整张原图
+     * @param image - 原图
+     * @param region - 调整后图片中的像素矩形 (x, y, 宽, 高)
+     */
+    pub fn apply_region(&self, image: &DynamicImage, region: (u32, u32, u32, u32)) -> DynamicImage {
+        let (x0, y0, width, height) = region;
+        let (source_width, source_height) = (image.width(), image.height());
+        let source = |x: u32, y: u32| {
+            let point = ((x0 + x) as f32 + 0.5, (y0 + y) as f32 + 0.5);
+            self.source_point(source_width, source_height, point)
+        };
+        resample_image(image, (width, height), source)
+    }
+}
+
+// 按图片的像素格式重采样（8 位和 16 位 RGB(A) 保留格式，其他格式转为 RGBA8）
+fn resample_image(image: &DynamicImage, size: (u32, u32), source: impl Fn(u32, u32) -> (f32, f32)) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgb8(buf) => DynamicImage::ImageRgb8(resample(buf, size, source)),
+        DynamicImage::ImageRgba8(buf) => DynamicImage::ImageRgba8(resample(buf, size, source)),
+        DynamicImage::ImageRgb16(buf) => DynamicImage::ImageRgb16(resample(buf, size, source)),
+        DynamicImage::ImageRgba16(buf) => DynamicImage::ImageRgba16(resample(buf, size, source)),
+        other => DynamicImage::ImageRgba8(resample(&other.to_rgba8(), size, source)),
+    }
+}
+
+// 按坐标映射对图片双线性重采样，超出边缘的位置取最近的边缘像素
+fn resample<P: Pixel>(
+    src: &ImageBuffer<P, Vec<P::Subpixel>>,
+    (width, height): (u32, u32),
+    source: impl Fn(u32, u32) -> (f32, f32),
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    let max_x = src.width() as f32 - 1.0;
+    let max_y = src.height() as f32 - 1.0;
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let (sx, sy) = source(x, y);
+        let fx = (sx - 0.5).clamp(0.0, max_x);
+        let fy = (sy - 0.5).clamp(0.0, max_y);
+        let (x0, y0) = (fx.floor() as u32, fy.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(src.width() - 1), (y0 + 1).min(src.height() - 1));
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+
+        let corners = [src.get_pixel(x0, y0), src.get_pixel(x1, y0), src.get_pixel(x0, y1), src.get_pixel(x1, y1)];
+        let weights = [(1.0 - tx) * (1.0 - ty), tx * (1.0 - ty), (1.0 - tx) * ty, tx * ty];
+        let mut pixel = *corners[0];
+        for (i, value) in pixel.channels_mut().iter_mut().enumerate() {
+            let mixed: f32 = corners
+                .iter()
+                .zip(weights)
+                .map(|(corner, weight)| corner.channels()[i].to_f32().unwrap_or(0.0) * weight)
+                .sum();
+            *value = NumCast::from(mixed.round()).unwrap_or(*value);
+        }
+        pixel
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    // 每个像素的颜色都不同的测试图片，便于按颜色找回像素位置
+    fn test_image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(width, height, |x, y| Rgb([x as u8 * 8, y as u8 * 8, 0])))
+    }
+
+    fn all_geometries() -> Vec<Geometry> {
+        let mut geometries = Vec::new();
+        for quarter_turns in 0..4 {
+            for flipped in [false, true] {
+                for crop in [None, Some(CropRect { x0: 0.25, y0: 0.1, x1: 0.75, y1: 0.6 })] {
+                    geometries.push(Geometry { quarter_turns, flipped, straighten: 0.0, crop });
+                }
+            }
+        }
+        geometries
+    }
+
+    #[test]
+    fn source_point_matches_apply() {
+        let image = test_image(20, 12);
+        for geometry in all_geometries() {
+            let output = geometry.apply(&image).to_rgb8();
+            assert_eq!(output.dimensions(), geometry.output_size(20, 12));
+            for (x, y, pixel) in output.enumerate_pixels() {
+                let (sx, sy) = geometry.source_point(20, 12, (x as f32 + 0.5, y as f32 + 0.5));
+                let expected = image.as_rgb8().unwrap().get_pixel(sx.floor() as u32, sy.floor() as u32);
+                assert_eq!(pixel, expected, "{:?} ({}, {})", geometry, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn apply_region_matches_apply() {
+        let image = test_image(24, 16);
+        let mut geometries = all_geometries();
+        geometries.push(Geometry { quarter_turns: 1, flipped: true, straighten: 7.5, crop: None });
+        geometries.push(Geometry { quarter_turns: 0, flipped: false, straighten: -12.0, crop: Some(CropRect { x0: 0.1, y0: 0.2, x1: 0.9, y1: 0.7 }) });
+        for geometry in geometries {
+            let full = geometry.apply(&image).to_rgb8();
+            let region = (2, 1, full.width() - 4, full.height() - 3);
+            let part = geometry.apply_region(&image, region).to_rgb8();
+            for (x, y, pixel) in part.enumerate_pixels() {
+                let expected = full.get_pixel(region.0 + x, region.1 + y);
+                // 拉直时两种算法的浮点误差可能让双线性插值的结果相差 1
+                for (a, b) in pixel.0.iter().zip(expected.0) {
+                    assert!(a.abs_diff(b) <= 1, "{:?} ({}, {}): {:?} != {:?}", geometry, x, y, pixel, expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rotate_and_flip_keep_cropped_content() {
+        let image = test_image(20, 12);
+        let crop = Some(CropRect { x0: 0.25, y0: 0.1, x1: 0.75, y1: 0.6 });
+        let base = Geometry { crop, ..Geometry::default() };
+        let reference = base.apply(&image).to_rgb8();
+        let expected = [
+            ("向右旋转", image::imageops::rotate90(&reference)),
+            ("向左旋转", image::imageops::rotate270(&reference)),
+            ("水平翻转", image::imageops::flip_horizontal(&reference)),
+            ("垂直翻转", image::imageops::flip_vertical(&reference)),
+        ];
+        for (i, (name, expected)) in expected.into_iter().enumerate() {
+            let mut geometry = base;
+            match i {
+                0 => geometry.rotate(true),
+                1 => geometry.rotate(false),
+                2 => geometry.flip(true),
+                _ => geometry.flip(false),
+            }
+            assert_eq!(geometry.apply(&image).to_rgb8(), expected, "{}", name);
+        }
+
+        // 旋转四次或翻转两次回到原状
+        let mut geometry = base;
+        for _ in 0..4 {
+            geometry.rotate(true);
+        }
+        assert_eq!(geometry.quarter_turns, 0);
+        assert_eq!(geometry.apply(&image).to_rgb8(), reference);
+        geometry.flip(true);
+        geometry.rotate(true);
+        geometry.flip(true);
+        geometry.rotate(true);
+        assert_eq!(geometry.apply(&image).to_rgb8(), reference);
+    }
+
+    #[test]
+    fn with_aspect_stays_inside_image() {
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        let cases = [
+            (CropRect::FULL, 2.0, 0, 0),
+            (CropRect::FULL, 0.5, 0, 0),
+            (CropRect { x0: 0.6, y0: 0.1, x1: 0.9, y1: 0.3 }, 1.0, 1, 0),
+            (CropRect { x0: 0.1, y0: 0.7, x1: 0.3, y1: 0.95 }, 3.0, -1, 1),
+            (CropRect { x0: 0.05, y0: 0.05, x1: 0.5, y1: 0.9 }, 0.2, 0, -1),
+        ];
+        for (crop, aspect, horizontal, vertical) in cases {
+            let result = crop.with_aspect(aspect, horizontal, vertical);
+            assert!(close(result.width() / result.height(), aspect), "{:?}", result);
+            assert!(result.x0 >= -1e-5 && result.y0 >= -1e-5, "{:?}", result);
+            assert!(result.x1 <= 1.0 + 1e-5 && result.y1 <= 1.0 + 1e-5, "{:?}", result);
+        }
+
+        // 拖动右边时左边保持不动，拖动上边时下边保持不动
+        let crop = CropRect { x0: 0.2, y0: 0.2, x1: 0.6, y1: 0.6 };
+        assert!(close(crop.with_aspect(2.0, 1, 0).x0, 0.2));
+        assert!(close(crop.with_aspect(0.5, 0, -1).y1, 0.6));
+
+        // 完整画面套用比例时以中心为准，取能放下的最大尺寸
+        let result = CropRect::FULL.with_aspect(2.0, 0, 0);
+        assert!(close(result.x0, 0.0) && close(result.x1, 1.0));
+        assert!(close(result.y0, 0.25) && close(result.y1, 0.75));
+    }
+}
@@ -0,0 +1,163 @@
+// ========== 直方图 ==========
+// 在后台线程统计原图的 RGB 和亮度直方图以及高光/暗部溢出比例，界面线程每帧检查结果。
+// 照片内嵌非 sRGB 配置文件时，采样的像素先转换为 sRGB 再统计，与预览看到的颜色一致。
+// 直方图面板和画框中的迷你直方图共用统计结果。
+
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+use image::DynamicImage;
+
+use crate::color::IccProfile;
+
+// 统计时最多采样的像素数：超过时按固定步长跳过像素，大尺寸照片也能很快完成，形状基本不变
+const MAX_SAMPLES: u64 = 4_000_000;
+
+/// 直方图通道
+#[derive(Clone, Copy, PartialEq)]
+pub enum Channel {
+    Red,
+    Green,
+    Blue,
+    Luma,
+}
+
+/// 直方图统计结果（8 位精度，16 位照片取高 8 位；按 sRGB 统计）
+pub struct Histogram {
+    red: [u32; 256],
+    green: [u32; 256],
+    blue: [u32; 256],
+    luma: [u32; 256],
+    samples: u64,    // 采样的像素数
+    shadows: u64,    // 任一通道为 0 的像素数（暗部溢出）
+    highlights: u64, // 任一通道为 255 的像素数（高光溢出）
+}
+
+impl Histogram {
+    /**
+     * 统计图片的直方图（按 Rec.709 系数计算亮度）
+     * @param icc - 照片内嵌的配置文件，统计前把采样的像素转换为 sRGB；None 时按 sRGB 统计
+     */
+    pub fn compute(image: &DynamicImage, icc: Option<&IccProfile>) -> Self {
+        let mut histogram = Self {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            luma: [0; 256],
+            samples: 0,
+            shadows: 0,
+            highlights: 0,
+        };
+        let pixels = image.width() as u64 * image.height() as u64;
+        let step = pixels.div_ceil(MAX_SAMPLES).max(1) as usize;
+        let mut samples: Vec<[u8; 3]> = match image {
+            DynamicImage::ImageRgb8(buffer) => {
+                buffer.as_raw().chunks_exact(3).step_by(step).map(|p| [p[0], p[1], p[2]]).collect()
+            }
+            DynamicImage::ImageRgba8(buffer) => {
+                buffer.as_raw().chunks_exact(4).step_by(step).map(|p| [p[0], p[1], p[2]]).collect()
+            }
+            DynamicImage::ImageRgb16(buffer) => buffer
+                .as_raw()
+                .chunks_exact(3)
+                .step_by(step)
+                .map(|p| [(p[0] >> 8) as u8, (p[1] >> 8) as u8, (p[2] >> 8) as u8])
+                .collect(),
+            DynamicImage::ImageRgba16(buffer) => buffer
+                .as_raw()
+                .chunks_exact(4)
+                .step_by(step)
+                .map(|p| [(p[0] >> 8) as u8, (p[1] >> 8) as u8, (p[2] >> 8) as u8])
+                .collect(),
+            _ => image.to_rgb8().as_raw().chunks_exact(3).step_by(step).map(|p| [p[0], p[1], p[2]]).collect(),
+        };
+        if let Some(icc) = icc {
+            icc.colors_to_srgb(&mut samples);
+        }
+        for [r, g, b] in samples {
+            histogram.add(r, g, b);
+        }
+        histogram
+    }
+
+    fn add(&mut self, r: u8, g: u8, b: u8) {
+        self.red[r as usize] += 1;
+        self.green[g as usize] += 1;
+        self.blue[b as usize] += 1;
+        let luma = 0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32;
+        self.luma[(luma.round() as usize).min(255)] += 1;
+        self.samples += 1;
+        if r.min(g).min(b) == 0 {
+            self.shadows += 1;
+        }
+        if r.max(g).max(b) == 255 {
+            self.highlights += 1;
+        }
+    }
+
+    fn bins(&self, channel: Channel) -> &[u32; 256] {
+        match channel {
+            Channel::Red => &self.red,
+            Channel::Green => &self.green,
+            Channel::Blue => &self.blue,
+            Channel::Luma => &self.luma,
+        }
+    }
+
+    /**
+     * 把直方图合并为指定数量的柱并归一化
+     * @param channel - 通道
+     * @param columns - 柱数量（256 的约数时每柱宽度相同）
+     * @return 各柱高度（0.0 ~ 1.0），以最高的柱为 1；用平方根压缩，避免个别尖峰把其余部分压平
+     */
+    pub fn heights(&self, channel: Channel, columns: usize) -> Vec<f32> {
+        let bins = self.bins(channel);
+        let columns = columns.clamp(1, 256);
+        let merged: Vec<f32> = (0..columns)
+            .map(|i| {
+                let (start, end) = (i * 256 / columns, (i + 1) * 256 / columns);
+                bins[start..end].iter().map(|&count| count as f32).sum::<f32>().sqrt()
+            })
+            .collect();
+        let max = merged.iter().cloned().fold(0.0, f32::max);
+        if max <= 0.0 {
+            return vec![0.0; columns];
+        }
+        merged.into_iter().map(|value| value / max).collect()
+    }
+
+    /// 暗部溢出比例（任一通道为 0 的像素占比）
+    pub fn shadow_clipping(&self) -> f32 {
+        self.shadows as f32 / self.samples.max(1) as f32
+    }
+
+    /// 高光溢出比例（任一通道为 255 的像素占比）
+    pub fn highlight_clipping(&self) -> f32 {
+        self.highlights as f32 / self.samples.max(1) as f32
+    }
+}
+
+/// 后台统计直方图
+pub fn start(image: Arc<DynamicImage>, icc: Option<Arc<IccProfile>>) -> Receiver<Histogram> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let histogram = Histogram::compute(&image, icc.as_deref());
+        // 标签页已关闭或图片已替换时接收端被丢弃，发送失败直接忽略
+        let _ = sender.send(histogram);
+    });
+    receiver
+}
+
+/// 检查后台统计是否完成：完成时取出结果，线程异常退出时放弃
+pub fn poll(receiver: &mut Option<Receiver<Histogram>>) -> Option<Histogram> {
+    let result = match receiver.as_ref()?.try_recv() {
+        Ok(histogram) => Some(histogram),
+        Err(TryRecvError::Empty) => return None,
+        Err(TryRecvError::Disconnected) => {
+            eprintln!("直方图统计线程异常退出");
+            None
+        }
+    };
+    *receiver = None;
+    result
+}
@@ -0,0 +1,162 @@
+// ========== 撤销/重做历史 ==========
+// 以快照形式记录编辑状态：每次修改完成后保存一份新状态，
+// 撤销、重做和历史面板中的跳转都只是移动当前位置。
+
+// 默认最多保留的历史记录数
+const DEFAULT_LIMIT: usize = 100;
+
+/// 一条历史记录：产生该状态的操作名称和操作后的状态
+struct Entry<T> {
+    label: String,
+    state: T,
+}
+
+/// 编辑历史
+pub struct History<T> {
+    entries: Vec<Entry<T>>, // entries[0] 为初始状态
+    position: usize,        // 当前状态在 entries 中的位置
+    limit: usize,
+}
+
+impl<T: Clone + PartialEq> History<T> {
+    pub fn new(label: impl Into<String>, initial: T) -> Self {
+        Self {
+            entries: vec![Entry { label: label.into(), state: initial }],
+            position: 0,
+            limit: DEFAULT_LIMIT,
+        }
+    }
+
+    /// 当前状态
+    pub fn current(&self) -> &T {
+        &self.entries[self.position].state
+    }
+
+    /// 记录一次修改；状态与当前相同时忽略。撤销后再修改会丢弃可重做的记录
+    pub fn record(&mut self, label: impl Into<String>, state: T) {
+        if *self.current() == state {
+            return;
+        }
+        self.entries.truncate(self.position + 1);
+        self.entries.push(Entry { label: label.into(), state });
+        // 超出上限时丢弃最早的记录
+        if self.entries.len() > self.limit {
+            let overflow = self.entries.len() - self.limit;
+            self.entries.drain(..overflow);
+        }
+        self.position = self.entries.len() - 1;
+    }
+
+    /// 清空历史并以新状态作为起点
+    pub fn reset(&mut self, label: impl Into<String>, initial: T) {
+        *self = Self { limit: self.limit, ..Self::new(label, initial) };
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.position > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.position + 1 < self.entries.len()
+    }
+
+    /// 撤销，返回撤销后的状态
+    pub fn undo(&mut self) -> Option<&T> {
+        if !self.can_undo() {
+            return None;
+        }
+        self.position -= 1;
+        Some(self.current())
+    }
+
+    /// 重做，返回重做后的状态
+    pub fn redo(&mut self) -> Option<&T> {
+        if !self.can_redo() {
+            return None;
+        }
+        self.position += 1;
+        Some(self.current())
+    }
+
+    /// 跳转到指定记录，返回跳转后的状态
+    pub fn jump_to(&mut self, index: usize) -> Option<&T> {
+        if index >= self.entries.len() || index == self.position {
+            return None;
+        }
+        self.position = index;
+        Some(self.current())
+    }
+
+    /// 当前位置
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// 所有记录的操作名称（从旧到新）
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.label.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(history: &History<i32>) -> Vec<&str> {
+        history.labels().collect()
+    }
+
+    #[test]
+    fn undo_redo_and_jump() {
+        let mut history = History::new("初始状态", 0);
+        assert!(!history.can_undo() && !history.can_redo());
+        history.record("一", 1);
+        history.record("二", 2);
+        // 状态没有变化时不记录
+        history.record("重复", 2);
+        assert_eq!(labels(&history), ["初始状态", "一", "二"]);
+
+        assert_eq!(history.undo(), Some(&1));
+        assert_eq!(history.undo(), Some(&0));
+        assert_eq!(history.undo(), None);
+        assert_eq!(history.redo(), Some(&1));
+        assert_eq!(history.position(), 1);
+
+        assert_eq!(history.jump_to(2), Some(&2));
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.jump_to(2), None);
+        assert_eq!(history.jump_to(3), None);
+        assert_eq!(history.jump_to(0), Some(&0));
+        assert_eq!(*history.current(), 0);
+    }
+
+    #[test]
+    fn record_after_undo_drops_redo_tail() {
+        let mut history = History::new("初始状态", 0);
+        history.record("一", 1);
+        history.record("二", 2);
+        history.undo();
+        history.record("三", 3);
+        assert_eq!(labels(&history), ["初始状态", "一", "三"]);
+        assert!(!history.can_redo());
+        assert_eq!(history.undo(), Some(&1));
+    }
+
+    #[test]
+    fn oldest_entries_are_dropped_over_limit() {
+        let mut history = History::new("初始状态", 0);
+        for i in 1..=DEFAULT_LIMIT as i32 + 20 {
+            history.record(i.to_string(), i);
+        }
+        assert_eq!(history.labels().count(), DEFAULT_LIMIT);
+        assert_eq!(history.position(), DEFAULT_LIMIT - 1);
+        assert_eq!(history.labels().next(), Some("21"));
+        assert_eq!(history.jump_to(0), Some(&21));
+        assert!(!history.can_undo());
+
+        // 重置后沿用上限，从新状态重新开始
+        history.reset("打开", 100);
+        assert_eq!(labels(&history), ["打开"]);
+        assert_eq!(*history.current(), 100);
+    }
+}
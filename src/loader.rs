@@ -0,0 +1,509 @@
+// ========== 后台加载图片 ==========
+// 在工作线程中解码图片（只解码一次，纹理和导出共用，并按 EXIF 方向摆正）、读取 EXIF、提取主色和调色板，
+// 照片内嵌非 sRGB 配置文件时，预览纹理和提取的颜色转换为 sRGB 显示，
+// 界面线程每帧检查结果。选择其他文件时丢弃正在进行的加载：解码时每次读取文件都检查取消标记，
+// 边读边解码的格式（PNG 等）可以在解码中途退出；JPEG 解码器先把整个文件读入内存再解码，
+// 读完后的解码过程无法中断，只能在解码结束、下一个阶段开始前退出。
+// 剪贴板粘贴的图片直接使用内存中的像素，跳过解码和读取 EXIF，不写入任何文件。
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+use eframe::egui::ColorImage;
+use image::metadata::Orientation;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::{DynamicImage, ImageDecoder, ImageEncoder, ImageFormat};
+
+use crate::color::{self, IccProfile};
+use crate::{compose, palette, TableRow};
+
+// 加载各阶段的名称（用于进度显示）
+const STAGES: [&str; 4] = ["解码图片", "生成预览", "读取EXIF", "提取颜色"];
+
+// 预览纹理的最大边长：预览区域远小于大尺寸照片，缩小后上传可节省显存，
+// 也避免 50~100MP 的照片超出显卡支持的最大纹理尺寸
+const PREVIEW_MAX_SIDE: u32 = 4096;
+
+/// 加载完成后图片放到哪个标签页
+#[derive(Clone, Copy, PartialEq)]
+pub enum LoadTarget {
+    NewTab,       // 新建标签页
+    Replace(u64), // 替换指定标签页中的图片
+}
+
+/// 工作线程的加载结果
+pub struct LoadedImage {
+    pub image: DynamicImage,      // 原始图片（已按 EXIF 方向摆正，保留位深，导出时使用）
+    pub exif: Option<Vec<u8>>,    // 原始 EXIF 数据块（方向已重置为 1），导出时写入
+    pub icc: Option<Arc<IccProfile>>, // 内嵌的非 sRGB 配置文件（原图像素位于该色彩空间）
+    pub preview: ColorImage,      // 缩小后的预览纹理数据
+    pub rows: Vec<TableRow>,      // EXIF 表格
+    pub dominant_color: [u8; 3],
+    pub palette: Vec<[u8; 3]>,
+}
+
+// 加载的图片来源
+enum Source {
+    File(String),        // 图片文件路径
+    Pixels(DynamicImage), // 内存中的图片（剪贴板粘贴）
+}
+
+/// 正在进行的加载；被丢弃时通知工作线程取消
+pub struct PendingLoad {
+    pub path: Option<String>, // 图片文件路径，内存中的图片为 None
+    pub name: String,         // 显示的文件名
+    pub target: LoadTarget,
+    stage: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    receiver: Receiver<Result<LoadedImage, String>>,
+}
+
+impl PendingLoad {
+    /// 启动工作线程加载图片
+    /// @param default_rows - 空白 EXIF 表格（读取 EXIF 的起点）
+    /// @param palette_size - 调色板颜色数量
+    /// @param max_texture_side - 显卡支持的最大纹理边长
+    pub fn start(
+        path: String,
+        target: LoadTarget,
+        default_rows: Vec<TableRow>,
+        palette_size: usize,
+        max_texture_side: usize,
+    ) -> Self {
+        let name = Path::new(&path)
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone());
+        Self::spawn(Some(path.clone()), name, Source::File(path), target, default_rows, palette_size, max_texture_side)
+    }
+
+    /// 启动工作线程加载内存中的图片（没有 EXIF 和配置文件，按 sRGB 处理）
+    /// @param name - 显示的文件名，同时作为导出时的默认文件名
+    pub fn start_pixels(
+        name: String,
+        image: DynamicImage,
+        target: LoadTarget,
+        default_rows: Vec<TableRow>,
+        palette_size: usize,
+        max_texture_side: usize,
+    ) -> Self {
+        Self::spawn(None, name, Source::Pixels(image), target, default_rows, palette_size, max_texture_side)
+    }
+
+    fn spawn(
+        path: Option<String>,
+        name: String,
+        source: Source,
+        target: LoadTarget,
+        default_rows: Vec<TableRow>,
+        palette_size: usize,
+        max_texture_side: usize,
+    ) -> Self {
+        let stage = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+
+        let (worker_stage, worker_cancel) = (stage.clone(), cancel.clone());
+        std::thread::spawn(move || {
+            let preview_side = PREVIEW_MAX_SIDE.min(max_texture_side as u32);
+            let result = load(source, default_rows, palette_size, preview_side, &worker_stage, &worker_cancel);
+            // 接收端已丢弃（加载被取消）时发送失败，直接忽略
+            let _ = sender.send(result);
+        });
+
+        Self { path, name, target, stage, cancel, receiver }
+    }
+
+    /// 检查加载是否完成
+    pub fn poll(&self) -> Option<Result<LoadedImage, String>> {
+        match self.receiver.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err("加载线程异常退出".to_string())),
+        }
+    }
+
+    /// 当前阶段名称和进度（0.0 ~ 1.0）
+    pub fn progress(&self) -> (&'static str, f32) {
+        let stage = self.stage.load(Ordering::Relaxed).min(STAGES.len() - 1);
+        (STAGES[stage], stage as f32 / STAGES.len() as f32)
+    }
+}
+
+impl Drop for PendingLoad {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+// 工作线程：解码、生成预览、读取 EXIF、提取颜色，每个阶段开始前检查是否已取消
+fn load(
+    source: Source,
+    default_rows: Vec<TableRow>,
+    palette_size: usize,
+    preview_side: u32,
+    stage: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> Result<LoadedImage, String> {
+    let enter = |index: usize| {
+        if cancel.load(Ordering::Relaxed) {
+            return Err("加载已取消".to_string());
+        }
+        stage.store(index, Ordering::Relaxed);
+        Ok(())
+    };
+
+    enter(0)?;
+    let (decoded, path) = match source {
+        Source::File(path) => (decode_oriented(Path::new(&path), Some(cancel))?, Some(path)),
+        Source::Pixels(image) => (Decoded { image, exif: None, icc: None, orientation: Orientation::NoTransforms }, None),
+    };
+    let Decoded { image, exif, icc, orientation } = decoded;
+    let icc = icc.and_then(color::parse).map(Arc::new);
+
+    enter(1)?;
+    // 超过预览尺寸时按面积平均缩小（原图保留给导出），小图直接使用原尺寸；纹理按 sRGB 显示
+    let small;
+    let source = if image.width().max(image.height()) > preview_side {
+        small = image.thumbnail(preview_side, preview_side);
+        &small
+    } else {
+        &image
+    };
+    let rgba = match &icc {
+        Some(icc) => icc.to_srgb(source).to_rgba8(),
+        None => source.to_rgba8(),
+    };
+    let preview = ColorImage::from_rgba_unmultiplied([rgba.width() as usize, rgba.height() as usize], &rgba);
+    drop(rgba);
+
+    enter(2)?;
+    let mut rows = match &path {
+        Some(path) => crate::read_exif(path, default_rows),
+        None => default_rows,
+    };
+    // EXIF 中的宽高是存储方向的尺寸，竖拍照片摆正后宽高互换
+    if swaps_dimensions(orientation) {
+        swap_dimension_rows(&mut rows);
+    }
+
+    enter(3)?;
+    let mut dominant_color = compose::dominant_color(&image);
+    let mut palette = palette::extract_palette(&image, palette_size);
+    // 画框背景和色板按 sRGB 绘制，提取的颜色也转换为 sRGB
+    if let Some(icc) = &icc {
+        icc.colors_to_srgb(std::slice::from_mut(&mut dominant_color));
+        icc.colors_to_srgb(&mut palette);
+    }
+
+    Ok(LoadedImage { image, exif, icc, preview, rows, dominant_color, palette })
+}
+
+/// 解码结果
+pub struct Decoded {
+    pub image: DynamicImage,        // 摆正后的图片（像素位于内嵌配置文件的色彩空间）
+    pub exif: Option<Vec<u8>>,      // 方向已重置为 1 的 EXIF 数据块
+    pub icc: Option<Vec<u8>>,       // 内嵌的 ICC 配置文件（JPEG APP2 / PNG iCCP）
+    pub orientation: Orientation,   // 原来的方向
+}
+
+/// 解码图片并按 EXIF 方向标记摆正（相机通常把竖拍照片按横向存储，再用方向标记指示如何旋转/翻转）
+/// @param cancel - 取消标记，设置后读取文件失败，解码随之中止（不需要取消时为 None）
+pub fn decode_oriented(path: &Path, cancel: Option<&AtomicBool>) -> Result<Decoded, String> {
+    // 直接从文件流解码，按内容识别格式（扩展名不对也能打开）
+    let file = File::open(path).map_err(|e| format!("无法读取文件: {}", e))?;
+    let reader = CancellableReader { inner: BufReader::new(file), cancel };
+    let mut decoder = image::ImageReader::new(reader)
+        .with_guessed_format()
+        .map_err(|e| format!("无法读取文件: {}", e))?
+        .into_decoder()
+        .map_err(|e| format!("无法解码图片: {}", e))?;
+
+    // 像素摆正后把 EXIF 中的方向改为 1，导出文件写入这份 EXIF 时不会被其他软件再旋转一次
+    let mut exif = decoder.exif_metadata().unwrap_or_else(|e| {
+        eprintln!("无法读取EXIF数据块: {}", e);
+        None
+    });
+    let orientation = exif
+        .as_deref_mut()
+        .and_then(Orientation::remove_from_exif_chunk)
+        .unwrap_or(Orientation::NoTransforms);
+    let icc = decoder.icc_profile().unwrap_or_else(|e| {
+        eprintln!("无法读取ICC配置文件: {}", e);
+        None
+    });
+
+    let mut image = DynamicImage::from_decoder(decoder).map_err(|e| format!("无法解码图片: {}", e))?;
+    image.apply_orientation(orientation);
+    Ok(Decoded { image, exif, icc, orientation })
+}
+
+// 每次读取前检查取消标记的文件读取器
+struct CancellableReader<'a> {
+    inner: BufReader<File>,
+    cancel: Option<&'a AtomicBool>,
+}
+
+impl CancellableReader<'_> {
+    fn check(&self) -> io::Result<()> {
+        match self.cancel {
+            Some(cancel) if cancel.load(Ordering::Relaxed) => Err(io::Error::other("加载已取消")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Read for CancellableReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.check()?;
+        self.inner.read(buf)
+    }
+}
+
+impl BufRead for CancellableReader<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.check()?;
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.inner.consume(amount);
+    }
+}
+
+impl Seek for CancellableReader<'_> {
+    fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(position)
+    }
+}
+
+/**
+ * 生成写入导出文件的 EXIF 数据块：图像尺寸改为导出尺寸，并去掉第二个 IFD（内嵌缩略图）。
+ * 原图的缩略图没有摆正，也不包含画框，留着会让查看器显示错误的预览。
+ * 直接修改原数据块中的数值，其余字段（包括厂商注释中的偏移）保持不变
+ * @param exif - 原图的 EXIF 数据块（TIFF 结构）
+ * @param width, height - 导出图片的尺寸
+ */
+pub fn export_exif(exif: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let mut data = exif.to_vec();
+    if patch_exif(&mut data, width, height).is_none() {
+        eprintln!("EXIF数据块结构异常，尺寸和缩略图未更新");
+    }
+    data
+}
+
+/**
+ * 按导出格式编码图片并写入 EXIF 和配置文件：JPEG 不支持透明通道和 16 位，转为 8 位 RGB；PNG 按 8 位 RGBA 写入
+ * @param exif - 导出用的 EXIF 数据块（export_exif 的结果）
+ * @param icc - 要嵌入的配置文件，None 时按 sRGB 解读
+ */
+pub fn encode_export<W: Write>(
+    writer: W,
+    image: &DynamicImage,
+    format: ImageFormat,
+    exif: Option<Vec<u8>>,
+    icc: Option<Vec<u8>>,
+) -> image::ImageResult<()> {
+    match format {
+        ImageFormat::Jpeg => {
+            let mut encoder = JpegEncoder::new(writer);
+            if let Some(exif) = exif
+                && let Err(e) = encoder.set_exif_metadata(exif)
+            {
+                eprintln!("无法写入EXIF数据: {}", e);
+            }
+            if let Some(icc) = icc
+                && let Err(e) = encoder.set_icc_profile(icc)
+            {
+                eprintln!("无法写入ICC配置文件: {}", e);
+            }
+            DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)
+        }
+        _ => {
+            let mut encoder = PngEncoder::new(writer);
+            if let Some(exif) = exif
+                && let Err(e) = encoder.set_exif_metadata(exif)
+            {
+                eprintln!("无法写入EXIF数据: {}", e);
+            }
+            if let Some(icc) = icc
+                && let Err(e) = encoder.set_icc_profile(icc)
+            {
+                eprintln!("无法写入ICC配置文件: {}", e);
+            }
+            DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)
+        }
+    }
+}
+
+// TIFF 标签编号和数据类型
+const TAG_IMAGE_WIDTH: u16 = 0x0100;
+const TAG_IMAGE_LENGTH: u16 = 0x0101;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_PIXEL_X_DIMENSION: u16 = 0xA002;
+const TAG_PIXEL_Y_DIMENSION: u16 = 0xA003;
+const TYPE_SHORT: u16 = 3;
+const TYPE_LONG: u16 = 4;
+
+// 按 TIFF 结构修改尺寸字段并断开 IFD1，数据越界时返回 None
+fn patch_exif(data: &mut [u8], width: u32, height: u32) -> Option<()> {
+    let big_endian = match data.get(..4)? {
+        b"MM\0*" => true,
+        b"II*\0" => false,
+        _ => return None,
+    };
+    let read16 = |data: &[u8], at: usize| -> Option<u16> {
+        let bytes = data.get(at..at + 2)?.try_into().ok()?;
+        Some(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    };
+    let read32 = |data: &[u8], at: usize| -> Option<u32> {
+        let bytes = data.get(at..at + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+    let write = |data: &mut [u8], at: usize, bytes: &[u8]| -> Option<()> {
+        data.get_mut(at..at + bytes.len())?.copy_from_slice(bytes);
+        Some(())
+    };
+    let to16 = |value: u16| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+    let to32 = |value: u32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+
+    // 把单值的尺寸字段改为新值：SHORT 放不下时改为 LONG（数值都存放在条目内，不需要移动数据）
+    let set_dimension = |data: &mut [u8], entry: usize, value: u32| -> Option<()> {
+        if read32(data, entry + 4)? != 1 {
+            return Some(());
+        }
+        match (read16(data, entry + 2)?, u16::try_from(value)) {
+            (TYPE_SHORT, Ok(short)) => {
+                write(data, entry + 8, &to16(short))?;
+                write(data, entry + 10, &[0, 0])
+            }
+            (TYPE_SHORT | TYPE_LONG, _) => {
+                write(data, entry + 2, &to16(TYPE_LONG))?;
+                write(data, entry + 8, &to32(value))
+            }
+            _ => Some(()),
+        }
+    };
+
+    // IFD0：更新宽高，记下 Exif IFD 的位置，最后把下一个 IFD 的偏移清零（去掉 IFD1）
+    let ifd0 = read32(data, 4)? as usize;
+    let count = read16(data, ifd0)? as usize;
+    let mut exif_ifd = None;
+    for index in 0..count {
+        let entry = ifd0 + 2 + index * 12;
+        match read16(data, entry)? {
+            TAG_IMAGE_WIDTH => set_dimension(data, entry, width)?,
+            TAG_IMAGE_LENGTH => set_dimension(data, entry, height)?,
+            TAG_EXIF_IFD => exif_ifd = Some(read32(data, entry + 8)? as usize),
+            _ => {}
+        }
+    }
+    write(data, ifd0 + 2 + count * 12, &to32(0))?;
+
+    // Exif IFD：更新像素尺寸
+    if let Some(exif_ifd) = exif_ifd {
+        let count = read16(data, exif_ifd)? as usize;
+        for index in 0..count {
+            let entry = exif_ifd + 2 + index * 12;
+            match read16(data, entry)? {
+                TAG_PIXEL_X_DIMENSION => set_dimension(data, entry, width)?,
+                TAG_PIXEL_Y_DIMENSION => set_dimension(data, entry, height)?,
+                _ => {}
+            }
+        }
+    }
+    Some(())
+}
+
+/// 只读取文件头中的 ICC 配置文件，不解码像素（使用 EXIF 内嵌缩略图时按原图的配置文件转换颜色）
+pub fn read_icc_profile(path: &Path) -> Option<Vec<u8>> {
+    let mut decoder = image::ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_decoder().ok()?;
+    decoder.icc_profile().ok().flatten()
+}
+
+/// 该方向是否需要旋转 90°（摆正后宽高互换）
+pub fn swaps_dimensions(orientation: Orientation) -> bool {
+    matches!(
+        orientation,
+        Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Rotate90FlipH | Orientation::Rotate270FlipH
+    )
+}
+
+// 交换 EXIF 表格中图像宽度和图像高度的值
+fn swap_dimension_rows(rows: &mut [TableRow]) {
+    let width = rows.iter().position(|row| row.tag_name == "图像宽度");
+    let height = rows.iter().position(|row| row.tag_name == "图像高度");
+    if let (Some(width), Some(height)) = (width, height) {
+        let value = std::mem::take(&mut rows[width].tag_value);
+        rows[width].tag_value = std::mem::replace(&mut rows[height].tag_value, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{Field, In, Reader, Tag, Value};
+    use std::io::Cursor;
+
+    // 带方向标记、像素尺寸和 IFD1 缩略图的 EXIF 数据块
+    fn camera_exif() -> Vec<u8> {
+        let orientation = Field { tag: Tag::Orientation, ifd_num: In::PRIMARY, value: Value::Short(vec![6]) };
+        let width = Field { tag: Tag::PixelXDimension, ifd_num: In::PRIMARY, value: Value::Long(vec![6000]) };
+        let height = Field { tag: Tag::PixelYDimension, ifd_num: In::PRIMARY, value: Value::Long(vec![4000]) };
+        let thumbnail = [0xFF, 0xD8, 0xFF, 0xD9];
+        let mut writer = exif::experimental::Writer::new();
+        writer.push_field(&orientation);
+        writer.push_field(&width);
+        writer.push_field(&height);
+        writer.set_jpeg(&thumbnail, In::THUMBNAIL);
+        let mut data = Cursor::new(Vec::new());
+        writer.write(&mut data, false).unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn jpeg_export_keeps_patched_exif_and_icc() {
+        // 与加载时相同：像素摆正后把方向改为 1
+        let mut exif = camera_exif();
+        assert!(Reader::new().read_raw(exif.clone()).unwrap().fields().any(|field| field.ifd_num == In::THUMBNAIL));
+        assert_eq!(Orientation::remove_from_exif_chunk(&mut exif), Some(Orientation::Rotate90));
+        let icc = moxcms::ColorProfile::new_display_p3().encode().unwrap();
+
+        // 导出图片带透明通道和 16 位像素，JPEG 编码前需要转换
+        let image = DynamicImage::ImageRgba16(image::ImageBuffer::from_pixel(8, 6, image::Rgba([40000, 20000, 10000, 65535])));
+        let mut jpeg = Vec::new();
+        let exif = export_exif(&exif, image.width(), image.height());
+        encode_export(&mut jpeg, &image, ImageFormat::Jpeg, Some(exif), Some(icc.clone())).unwrap();
+
+        let parsed = Reader::new().read_from_container(&mut Cursor::new(&jpeg)).unwrap();
+        let value = |tag: Tag| parsed.get_field(tag, In::PRIMARY).and_then(|field| field.value.get_uint(0));
+        assert_eq!(value(Tag::Orientation), Some(1));
+        assert_eq!(value(Tag::PixelXDimension), Some(8));
+        assert_eq!(value(Tag::PixelYDimension), Some(6));
+        assert!(parsed.fields().all(|field| field.ifd_num != In::THUMBNAIL));
+
+        let mut decoder = image::codecs::jpeg::JpegDecoder::new(Cursor::new(&jpeg)).unwrap();
+        assert_eq!(decoder.icc_profile().unwrap(), Some(icc));
+        assert_eq!(DynamicImage::from_decoder(decoder).unwrap().width(), 8);
+    }
+
+    #[test]
+    fn png_export_keeps_patched_exif() {
+        let exif = export_exif(&camera_exif(), 8, 6);
+        let image = DynamicImage::new_rgba8(8, 6);
+        let mut png = Vec::new();
+        encode_export(&mut png, &image, ImageFormat::Png, Some(exif), None).unwrap();
+
+        let mut decoder = image::codecs::png::PngDecoder::new(Cursor::new(&png)).unwrap();
+        let exif = decoder.exif_metadata().unwrap().unwrap();
+        let parsed = Reader::new().read_raw(exif).unwrap();
+        let value = |tag: Tag| parsed.get_field(tag, In::PRIMARY).and_then(|field| field.value.get_uint(0));
+        assert_eq!(value(Tag::PixelXDimension), Some(8));
+        assert_eq!(value(Tag::PixelYDimension), Some(6));
+    }
+}
@@ -1,5 +1,17 @@
 extern crate exif;
 
+mod color;
+mod compose;
+mod filmstrip;
+mod fonts;
+mod geometry;
+mod histogram;
+mod history;
+mod loader;
+mod palette;
+mod presets;
+mod text_render;
+
 use eframe::egui::{
     self, 
     FontData, 
@@ -9,44 +21,781 @@ use eframe::egui::{
     ComboBox
 };
 use std::{
+    hash::{Hash, Hasher},
     path::Path,
     fs::File,
     io::{BufReader, BufRead, Seek, SeekFrom},
-    collections::HashMap
+    collections::{HashMap, VecDeque},
+    sync::{mpsc::{self, Receiver, TryRecvError}, Arc}
 };
 use egui::{ColorImage, RichText, TextureHandle};
+use egui_dock::{tab_viewer::OnCloseResponse, DockArea, DockState, TabViewer, Translations};
+use serde::{Deserialize, Serialize};
 use nalgebra::Vector4;
 
 use exif::{Exif, In, Reader, Tag, Value};
-use rusttype::{Font, Scale, point};
+use filmstrip::Filmstrip;
+use fonts::{FontLibrary, FontSettings, FontWeight};
+use geometry::{CropHandle, CropRect, Geometry};
+use history::History;
+use loader::{LoadTarget, LoadedImage, PendingLoad};
+use presets::Preset;
+use text_render::{FontSource, TextStyle};
 
 struct MyEguiApp {
-    selected_file: Option<String>,
-    texture: Option<TextureHandle>,
-    image_size: egui::Vec2,
+    doc: ImageDoc, // 新增：当前激活的图片（左侧面板编辑的对象）
+    docs: HashMap<u64, ImageDoc>, // 新增：其他已打开的图片（按标签页编号索引）
+    dock_state: DockState<u64>, // 新增：图片标签页布局
+    next_doc_id: u64, // 新增：下一个标签页编号（0 保留给未打开图片时的空白文档）
+    filmstrip: Filmstrip, // 新增：当前文件夹的缩略图胶片条
+    pending_load: Option<PendingLoad>, // 新增：正在后台加载的图片
+    load_queue: VecDeque<String>, // 新增：一次打开多张图片时等待加载的文件
+    max_texture_side: usize, // 新增：显卡支持的最大纹理边长（每帧从 egui 读取，限制预览纹理尺寸）
+    show_loupe: bool, // 新增：预览中是否显示放大镜
+    accurate_preview: bool, // 新增：预览使用导出流程在后台渲染的结果
+    histogram_luma: bool, // 新增：直方图面板显示亮度（否则显示 RGB 三个通道）
+    crop_mode: bool, // 新增：预览区域显示裁剪框（拖动调整裁剪范围）
+    crop_ratio: CropRatio, // 新增：裁剪比例
+    crop_ratio_portrait: bool, // 新增：裁剪比例是否使用竖向（交换宽高）
     left_panel_width: f32,
-    rows: Vec<TableRow>,
     default_rows: Vec<TableRow>,
-    initial_exif_data: Vec<TableRow>, // 新增：保存第一次加载图片时的EXIF数据
 
+    recent_files: Vec<String>, // 新增：最近打开的文件（最新的在前）
+    last_export_dir: Option<std::path::PathBuf>, // 新增：上次导出的目录
+    clipboard: Option<arboard::Clipboard>, // 新增：系统剪贴板（首次复制/粘贴时创建）
+    preserve_icc_profile: bool, // 新增：导出时保留照片原来的色彩配置文件（否则转换为 sRGB）
+    presets: Vec<Preset>, // 新增：命名的画框预设
+    selected_preset: Option<usize>, // 新增：当前选中的预设
+    history_label: Option<String>, // 新增：下一条历史记录的操作名称（未设置时根据差异自动生成）
+    preset_name: String, // 新增：新建/重命名预设时输入的名称
+
+    font_library: FontLibrary, // 新增：扫描到的系统字体（启用 bundled-font 特性时另有内置字体）
+    ui_font_family: Option<String>, // 新增：界面字体族
+    export_font_family: Option<String>, // 新增：画框文字的默认字体族
+
+    show_custom_bg_color_picker: bool, // 新增：是否显示自定义背景颜色选择器
+
+    export_toast: Option<String>,       // 提示文本
+    export_toast_is_success: bool,      // 是否成功提示
+    toast_timer: Option<std::time::Instant>, // 新增：用于跟踪吐司显示时间
+}
+
+// ========== 新增：图片文档 ==========
+// 每个标签页对应一张图片，各自保存元数据、画框设置、预览纹理和编辑历史
+struct ImageDoc {
+    id: u64, // 标签页编号
+    selected_file: Option<String>, // 图片文件路径（剪贴板粘贴的图片没有文件，为 None）
+    file_name: String, // 标签页标题和导出时的默认文件名
+    texture: Option<TextureHandle>,
+    image_size: egui::Vec2, // 几何调整后的照片尺寸（边框和排版以此为准）
+    rows: Vec<TableRow>,
+    original_rows: Vec<TableRow>, // 图片加载时读取到的原始EXIF数据
+    frame: FrameSettings, // 这张图片的画框设置
+    geometry: Geometry, // 这张图片的旋转、翻转、拉直和裁剪（不修改原图，导出时执行）
+    history: History<EditState>, // 这张图片的撤销/重做历史
+    original_image: Option<Arc<image::DynamicImage>>, // 原始图片（与精确预览的渲染线程共享）
+    exif_metadata: Option<Vec<u8>>, // 原图的 EXIF 数据块（方向已重置），导出时写入
+    icc_profile: Option<Arc<color::IccProfile>>, // 原图内嵌的非 sRGB 配置文件（没有时按 sRGB 处理）
+
+    bg_texture: Option<Arc<image::RgbaImage>>, // 已加载的纹理图片
+    bg_texture_color: Option<egui::Color32>, // 纹理的平均颜色（加载纹理时计算，用于决定文字颜色）
+    dominant_color: Option<egui::Color32>, // 照片主色
+    bg_preview_texture: Option<(u64, TextureHandle)>, // 非纯色背景的预览纹理（附带参数哈希）
+    palette: Vec<egui::Color32>, // 从照片提取的调色板
+    text_preview_texture: Option<(u64, TextureHandle)>, // 预览中的画框文字纹理（附带参数哈希）
+    view: PreviewView, // 预览的缩放和平移
+    loupe_texture: Option<(Geometry, [u32; 4], TextureHandle)>, // 放大镜使用的原图局部纹理（附带调整参数和裁剪范围）
+    accurate_texture: Option<(u64, TextureHandle)>, // 精确预览最近一次渲染完成的纹理（附带参数哈希）
+    accurate_job: Option<(u64, Receiver<ColorImage>)>, // 正在后台渲染的精确预览（附带参数哈希）
+    frame_fingerprint: Option<(FrameSettings, u64)>, // 画框设置的哈希（设置变化时才重新计算）
+    histogram: Option<histogram::Histogram>, // 原图的直方图
+    histogram_job: Option<Receiver<histogram::Histogram>>, // 正在后台统计的直方图
+}
+
+// 预览缩放模式
+#[derive(Clone, Copy, PartialEq)]
+enum ZoomMode {
+    Fit,    // 完整显示（小图不放大）
+    Fill,   // 填满预览区域
+    Actual, // 100%：原图一个像素对应屏幕一个物理像素
+    Custom, // 滚轮缩放或拖动后的自定义视图
+}
+
+// 预览视图：缩放模式、比例和平移
+#[derive(Clone, Copy)]
+struct PreviewView {
+    mode: ZoomMode,
+    scale: f32,       // 每个原图像素对应的点数（每帧按模式更新）
+    pan: egui::Vec2,  // 画框中心相对预览区域中心的偏移（点）
+}
+
+impl Default for PreviewView {
+    fn default() -> Self {
+        Self { mode: ZoomMode::Fit, scale: 1.0, pan: egui::Vec2::ZERO }
+    }
+}
+
+// 预览缩放范围（以物理像素计：0.02 为 2%，32 为 3200%）
+const MIN_PREVIEW_ZOOM: f32 = 0.02;
+const MAX_PREVIEW_ZOOM: f32 = 32.0;
+// 放大镜边长（点）
+const LOUPE_SIZE: f32 = 220.0;
+// 裁剪框边角的拖动范围（点）
+const CROP_HANDLE_SIZE: f32 = 12.0;
+
+// 预览中照片的纹理来源
+enum PreviewPhoto<'a> {
+    Source(&'a TextureHandle),           // 原图预览纹理：按几何调整换算纹理坐标后绘制整张照片
+    Part(&'a TextureHandle, egui::Rect), // 已完成几何调整的局部纹理（放大镜），附带覆盖的照片像素范围
+    None,                                // 不绘制照片
+}
+
+impl ImageDoc {
+    // 创建未加载图片的文档，画框设置和纹理背景沿用给定的文档
+    fn blank(id: u64, rows: Vec<TableRow>, base: Option<&ImageDoc>) -> Self {
+        let frame = base.map(|doc| doc.frame.clone()).unwrap_or_default();
+        Self {
+            id,
+            selected_file: None,
+            file_name: "未命名".to_string(),
+            texture: None,
+            image_size: egui::Vec2::new(0.0, 0.0),
+            history: History::new("初始状态", EditState { rows: rows.clone(), frame: frame.clone(), geometry: Geometry::default() }),
+            original_rows: Vec::new(), // 初始为空向量
+            rows,
+            frame,
+            geometry: Geometry::default(),
+            original_image: None,
+            exif_metadata: None,
+            icc_profile: None,
+            bg_texture: base.and_then(|doc| doc.bg_texture.clone()),
+            bg_texture_color: base.and_then(|doc| doc.bg_texture_color),
+            dominant_color: None,
+            bg_preview_texture: None,
+            palette: Vec::new(),
+            text_preview_texture: None,
+            view: PreviewView::default(),
+            loupe_texture: None,
+            accurate_texture: None,
+            accurate_job: None,
+            frame_fingerprint: None,
+            histogram: None,
+            histogram_job: None,
+        }
+    }
+
+    // 使用加载线程的结果填充文档：纹理、原始图片、EXIF 表格和颜色信息
+    fn apply_loaded(&mut self, ctx: &egui::Context, path: Option<&str>, name: &str, loaded: LoadedImage) {
+        let [r, g, b] = loaded.dominant_color;
+        self.dominant_color = Some(egui::Color32::from_rgb(r, g, b));
+        self.palette = loaded.palette
+            .into_iter()
+            .map(|[r, g, b]| egui::Color32::from_rgb(r, g, b))
+            .collect();
+
+        // 存储原图尺寸（已按 EXIF 方向摆正），用于后续显示比例计算（边框按原图像素计算，与预览纹理的分辨率无关）
+        self.image_size = egui::Vec2::new(loaded.image.width() as f32, loaded.image.height() as f32);
+
+        // 文件名作为纹理名称
+        let texture_name = name;
+        // 预览纹理通常比显示区域大，缩小显示时使用 mipmap 三线性过滤，避免摩尔纹和锯齿
+        self.texture = Some(ctx.load_texture(
+            texture_name, // 纹理标识名称
+            loaded.preview, // 缩小后的预览数据
+            TextureOptions::LINEAR.with_mipmap_mode(Some(egui::TextureFilter::Linear)) // 纹理选项
+        ));
+        // 保留原始图片数据（位深/像素）用于导出
+        let original = Arc::new(loaded.image);
+        // 在后台统计新图片的直方图
+        self.histogram = None;
+        self.histogram_job = Some(histogram::start(original.clone(), loaded.icc.clone()));
+        self.original_image = Some(original);
+        self.exif_metadata = loaded.exif;
+        self.icc_profile = loaded.icc;
+        self.selected_file = path.map(str::to_string);
+        self.file_name = name.to_string();
+
+        // 保存这张图片的原始EXIF数据，用于重置和对比修改
+        self.rows = loaded.rows;
+        self.original_rows = self.rows.clone();
+        // 新图片不沿用之前的旋转和裁剪
+        self.geometry = Geometry::default();
+
+        // 打开新图片后重新开始记录编辑历史
+        self.history.reset(format!("打开 {}", texture_name), self.edit_state());
+    }
+
+    fn edit_state(&self) -> EditState {
+        EditState {
+            rows: self.rows.clone(),
+            frame: self.frame.clone(),
+            geometry: self.geometry,
+        }
+    }
+
+    // 原图尺寸（未加载图片时为 None）
+    fn source_size(&self) -> Option<(u32, u32)> {
+        self.original_image.as_ref().map(|image| (image.width(), image.height()))
+    }
+
+    // 按几何调整更新照片尺寸和 EXIF 表格中的宽高（没有调整时恢复原始值）
+    fn sync_geometry(&mut self) {
+        let Some((width, height)) = self.source_size() else {
+            return;
+        };
+        let (output_width, output_height) = self.geometry.output_size(width, height);
+        self.image_size = egui::vec2(output_width as f32, output_height as f32);
+        let identity = self.geometry.is_identity();
+        for (row, original) in self.rows.iter_mut().zip(&self.original_rows) {
+            let size = match row.tag_name.as_str() {
+                "图像宽度" => output_width,
+                "图像高度" => output_height,
+                _ => continue,
+            };
+            row.tag_value = if identity { original.tag_value.clone() } else { format!("{} px", size) };
+        }
+    }
+
+    // 画框设置的哈希：每帧只比较设置是否变化，变化时才序列化计算（精确预览的缓存键使用）
+    fn frame_fingerprint(&mut self) -> u64 {
+        if let Some((frame, fingerprint)) = &self.frame_fingerprint
+            && *frame == self.frame
+        {
+            return *fingerprint;
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        serde_json::to_string(&self.frame).unwrap_or_default().hash(&mut hasher);
+        let fingerprint = hasher.finish();
+        self.frame_fingerprint = Some((self.frame.clone(), fingerprint));
+        fingerprint
+    }
+
+    // 标签页标题：文件名
+    fn title(&self) -> String {
+        self.file_name.clone()
+    }
+}
+
+// 画框设置：边框、背景、文字样式等，会话之间持久化保存
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct FrameSettings {
     up_value: u32,
     down_value: u32,
     left_value: u32,
     right_value: u32,
+    border_mode: BorderMode, // 边框计算模式
+    percent_base: PercentBase, // 百分比边框的参照边
+    up_percent: f32, // 百分比模式下的四边边框
+    down_percent: f32,
+    left_percent: f32,
+    right_percent: f32,
+    canvas_ratio: CanvasRatio, // 画布目标比例
+    canvas_ratio_swapped: bool, // 是否交换比例的宽高（如 4:5 -> 5:4）
+    canvas_margin_percent: f32, // 最小留白，占长边的百分比
+    canvas_offset_x: f32, // 图片水平偏移（-1 靠左，0 居中，1 靠右）
+    canvas_offset_y: f32, // 图片垂直偏移（-1 靠上，0 居中，1 靠下）
+    corner_radius_percent: f32, // 照片圆角半径，占短边的百分比
+    shadow_enabled: bool, // 是否绘制投影
+    shadow_offset_x_percent: f32, // 投影水平偏移，占短边的百分比
+    shadow_offset_y_percent: f32, // 投影垂直偏移，占短边的百分比
+    shadow_blur_percent: f32, // 投影模糊半径，占短边的百分比
+    shadow_opacity: f32, // 投影不透明度（0~1）
     min_value: f64,
     max_value: f64,
     step: f64,
     decimal_places: usize,
-    bg_color: egui::Color32, // 新增：背景颜色
-    enable_blur_bg: bool, // 新增：是否启用模糊背景
-    blur_strength: f32, // 新增：模糊强度
+    bg_color: egui::Color32, // 背景颜色
+    bg_kind: BackgroundKind, // 背景类型
+    bg_gradient_end: egui::Color32, // 渐变结束颜色（起始颜色为 bg_color）
+    bg_gradient_angle: f32, // 线性渐变角度（度）
+    bg_texture_path: Option<String>, // 纹理图片路径
+    bg_texture_fit: compose::TextureFit, // 纹理平铺或拉伸
+    text_color: Option<egui::Color32>, // 自定义文字颜色，None 时按背景亮度自动选择黑/白
+    show_palette_strip: bool, // 是否在画框中绘制色板条
+    show_histogram: bool, // 是否在画框中绘制迷你直方图
+    frame_template: FrameTemplate, // 画框文字风格
+    field_styles: Vec<FieldStyle>, // 各字段的文字样式（与 FrameField::ALL 对应）
+    enable_blur_bg: bool, // 是否启用模糊背景
+    blur_strength: f32, // 模糊强度
+}
 
-    show_custom_bg_color_picker: bool, // 新增：是否显示自定义背景颜色选择器
+impl Default for FrameSettings {
+    fn default() -> Self {
+        Self {
+            up_value: 0,
+            down_value: 75,
+            left_value: 0,
+            right_value: 0,
+            border_mode: BorderMode::Pixel, // 默认使用像素边框
+            percent_base: PercentBase::ShortEdge,
+            up_percent: 0.0,
+            down_percent: 10.0, // 默认底部留出短边10%的文字区域
+            left_percent: 0.0,
+            right_percent: 0.0,
+            canvas_ratio: CanvasRatio::Square,
+            canvas_ratio_swapped: false,
+            canvas_margin_percent: 5.0, // 默认留白为长边的5%
+            canvas_offset_x: 0.0,
+            canvas_offset_y: 0.0,
+            corner_radius_percent: 0.0, // 默认直角
+            shadow_enabled: false,
+            shadow_offset_x_percent: 0.5,
+            shadow_offset_y_percent: 1.0,
+            shadow_blur_percent: 2.0,
+            shadow_opacity: 0.35,
+            min_value: 0.0,
+            max_value: 100.0,
+            step: 1.0,
+            decimal_places: 1,
+            bg_color: egui::Color32::from_rgba_premultiplied(255, 255, 255, 255), // 默认白色背景
+            bg_kind: BackgroundKind::Solid,
+            bg_gradient_end: egui::Color32::from_rgb(200, 200, 200),
+            bg_gradient_angle: 90.0, // 默认从上到下
+            bg_texture_path: None,
+            bg_texture_fit: compose::TextureFit::Tile,
+            text_color: None, // 默认自动选择文字颜色
+            show_palette_strip: false,
+            show_histogram: false,
+            frame_template: FrameTemplate::Classic,
+            field_styles: FrameTemplate::Classic.field_styles(),
+            enable_blur_bg: false, // 默认不启用模糊背景
+            blur_strength: 1.0, // 默认模糊强度
+        }
+    }
+}
 
-    original_image: Option<image::DynamicImage>,
-    export_toast: Option<String>,       // 提示文本
-    export_toast_is_success: bool,      // 是否成功提示
-    toast_timer: Option<std::time::Instant>, // 新增：用于跟踪吐司显示时间
+// 可撤销的编辑状态：EXIF 表格和画框设置
+#[derive(Clone, PartialEq)]
+struct EditState {
+    rows: Vec<TableRow>,
+    frame: FrameSettings,
+    geometry: Geometry,
+}
+
+impl EditState {
+    // 根据两次状态的差异生成历史记录的操作名称
+    fn describe_change(&self, new: &EditState) -> String {
+        if self.geometry != new.geometry {
+            let (old, new) = (&self.geometry, &new.geometry);
+            return if old.crop != new.crop && (old.quarter_turns, old.flipped) == (new.quarter_turns, new.flipped) {
+                "裁剪".to_string()
+            } else if old.straighten != new.straighten && old.flipped == new.flipped {
+                "拉直".to_string()
+            } else {
+                "旋转/翻转".to_string()
+            };
+        }
+        if let Some(row) = new.rows.iter().zip(&self.rows).find(|(a, b)| a != b).map(|(a, _)| a) {
+            return format!("编辑 {}", row.tag_name);
+        }
+        let (old, new) = (&self.frame, &new.frame);
+        if old.frame_template != new.frame_template {
+            format!("切换文字风格为「{}」", new.frame_template.label())
+        } else if old.field_styles != new.field_styles {
+            "修改字段样式".to_string()
+        } else if old.bg_color != new.bg_color || old.bg_gradient_end != new.bg_gradient_end || old.text_color != new.text_color {
+            "修改颜色".to_string()
+        } else if old.bg_kind != new.bg_kind
+            || old.bg_gradient_angle != new.bg_gradient_angle
+            || old.bg_texture_path != new.bg_texture_path
+            || old.bg_texture_fit != new.bg_texture_fit
+        {
+            "修改背景".to_string()
+        } else if old.corner_radius_percent != new.corner_radius_percent
+            || old.shadow_enabled != new.shadow_enabled
+            || old.shadow_offset_x_percent != new.shadow_offset_x_percent
+            || old.shadow_offset_y_percent != new.shadow_offset_y_percent
+            || old.shadow_blur_percent != new.shadow_blur_percent
+            || old.shadow_opacity != new.shadow_opacity
+        {
+            "修改圆角/投影".to_string()
+        } else if old.border_mode != new.border_mode {
+            format!("切换边框模式为「{}」", new.border_mode.label())
+        } else if (old.up_value, old.down_value, old.left_value, old.right_value) != (new.up_value, new.down_value, new.left_value, new.right_value)
+            || (old.up_percent, old.down_percent, old.left_percent, old.right_percent) != (new.up_percent, new.down_percent, new.left_percent, new.right_percent)
+            || old.percent_base != new.percent_base
+            || old.canvas_ratio != new.canvas_ratio
+            || old.canvas_ratio_swapped != new.canvas_ratio_swapped
+            || old.canvas_margin_percent != new.canvas_margin_percent
+            || (old.canvas_offset_x, old.canvas_offset_y) != (new.canvas_offset_x, new.canvas_offset_y)
+        {
+            "修改边框".to_string()
+        } else {
+            "修改设置".to_string()
+        }
+    }
+}
+
+// 边框模式
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum BorderMode {
+    Pixel,       // 像素：直接使用上下左右的像素值
+    Percent,     // 百分比：按原图短边或长边的百分比计算
+    AspectRatio, // 画布比例：按目标宽高比和最小留白自动计算边框
+}
+
+impl BorderMode {
+    fn label(&self) -> &'static str {
+        match self {
+            BorderMode::Pixel => "像素",
+            BorderMode::Percent => "百分比",
+            BorderMode::AspectRatio => "画布比例",
+        }
+    }
+}
+
+// 百分比边框的参照边
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum PercentBase {
+    ShortEdge, // 短边
+    LongEdge,  // 长边
+}
+
+// 画布目标比例（宽:高）
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum CanvasRatio {
+    Square,   // 1:1
+    Portrait, // 4:5
+    Story,    // 9:16
+    Classic,  // 3:2
+}
+
+impl CanvasRatio {
+    const ALL: [CanvasRatio; 4] = [
+        CanvasRatio::Square,
+        CanvasRatio::Portrait,
+        CanvasRatio::Story,
+        CanvasRatio::Classic,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            CanvasRatio::Square => "1:1",
+            CanvasRatio::Portrait => "4:5",
+            CanvasRatio::Story => "9:16",
+            CanvasRatio::Classic => "3:2",
+        }
+    }
+
+    fn ratio(&self) -> (u32, u32) {
+        match self {
+            CanvasRatio::Square => (1, 1),
+            CanvasRatio::Portrait => (4, 5),
+            CanvasRatio::Story => (9, 16),
+            CanvasRatio::Classic => (3, 2),
+        }
+    }
+}
+
+// 裁剪比例
+#[derive(Clone, Copy, PartialEq)]
+enum CropRatio {
+    Free,     // 自由裁剪
+    Original, // 原始比例
+    Square,   // 1:1
+    Standard, // 4:3
+    Classic,  // 3:2
+    Wide,     // 16:9
+}
+
+impl CropRatio {
+    const ALL: [CropRatio; 6] = [
+        CropRatio::Free,
+        CropRatio::Original,
+        CropRatio::Square,
+        CropRatio::Standard,
+        CropRatio::Classic,
+        CropRatio::Wide,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            CropRatio::Free => "自由",
+            CropRatio::Original => "原始比例",
+            CropRatio::Square => "1:1",
+            CropRatio::Standard => "4:3",
+            CropRatio::Classic => "3:2",
+            CropRatio::Wide => "16:9",
+        }
+    }
+
+    // 像素宽高比（宽 / 高），自由裁剪为 None；width/height 为裁剪前的图片尺寸
+    fn aspect(&self, width: u32, height: u32) -> Option<f32> {
+        let (w, h) = match self {
+            CropRatio::Free => return None,
+            CropRatio::Original => (width.max(height), width.min(height)),
+            CropRatio::Square => (1, 1),
+            CropRatio::Standard => (4, 3),
+            CropRatio::Classic => (3, 2),
+            CropRatio::Wide => (16, 9),
+        };
+        Some(w as f32 / h as f32)
+    }
+}
+
+// 背景类型
+#[derive(Clone, Copy, PartialEq, Hash, Serialize, Deserialize)]
+enum BackgroundKind {
+    Solid,          // 纯色
+    LinearGradient, // 线性渐变
+    RadialGradient, // 径向渐变
+    DominantColor,  // 照片主色
+    Texture,        // 纹理图片
+}
+
+impl BackgroundKind {
+    const ALL: [BackgroundKind; 5] = [
+        BackgroundKind::Solid,
+        BackgroundKind::LinearGradient,
+        BackgroundKind::RadialGradient,
+        BackgroundKind::DominantColor,
+        BackgroundKind::Texture,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            BackgroundKind::Solid => "纯色",
+            BackgroundKind::LinearGradient => "线性渐变",
+            BackgroundKind::RadialGradient => "径向渐变",
+            BackgroundKind::DominantColor => "照片主色",
+            BackgroundKind::Texture => "纹理图片",
+        }
+    }
+}
+
+// 画框中显示的 EXIF 字段（按从左到右的顺序）
+#[derive(Clone, Copy, PartialEq)]
+enum FrameField {
+    Model,
+    Iso,
+    Aperture,
+    Exposure,
+    FocalLength,
+}
+
+impl FrameField {
+    const ALL: [FrameField; 5] = [
+        FrameField::Model,
+        FrameField::Iso,
+        FrameField::Aperture,
+        FrameField::Exposure,
+        FrameField::FocalLength,
+    ];
+
+    // 对应 EXIF 表格中的标签名
+    fn tag_name(&self) -> &'static str {
+        match self {
+            FrameField::Model => "相机型号",
+            FrameField::Iso => "ISO",
+            FrameField::Aperture => "光圈",
+            FrameField::Exposure => "曝光时长",
+            FrameField::FocalLength => "焦距",
+        }
+    }
+
+    // 画框中显示的文字
+    fn display_text(&self, value: &str) -> String {
+        match self {
+            FrameField::Iso => format!("ISO{}", value),
+            _ => value.to_string(),
+        }
+    }
+}
+
+// 单个画框字段的文字样式
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+struct FieldStyle {
+    font_family: Option<String>, // 字体族，None 时使用导出字体
+    font_style: Option<String>,  // 字体族中的具体样式（对应字体集合中的某个 face），None 时按字重选择
+    weight: FontWeight,
+    size_ratio: f32,             // 字号倍数（相对文字区域高度的60%）
+    color: Option<egui::Color32>, // None 时使用全局文字颜色
+}
+
+impl FieldStyle {
+    fn new(weight: FontWeight, size_ratio: f32) -> Self {
+        Self { font_family: None, font_style: None, weight, size_ratio, color: None }
+    }
+}
+
+// 画框文字风格，提供各字段样式的默认值
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum FrameTemplate {
+    Classic, // 经典：型号粗体，参数常规
+    Minimal, // 极简：整体偏小，参数细体
+    Bold,    // 醒目：型号放大加粗
+}
+
+impl FrameTemplate {
+    const ALL: [FrameTemplate; 3] = [FrameTemplate::Classic, FrameTemplate::Minimal, FrameTemplate::Bold];
+
+    fn label(&self) -> &'static str {
+        match self {
+            FrameTemplate::Classic => "经典",
+            FrameTemplate::Minimal => "极简",
+            FrameTemplate::Bold => "醒目",
+        }
+    }
+
+    // 返回与 FrameField::ALL 一一对应的字段样式
+    fn field_styles(&self) -> Vec<FieldStyle> {
+        let (model, params) = match self {
+            FrameTemplate::Classic => (FieldStyle::new(FontWeight::Bold, 1.0), FieldStyle::new(FontWeight::Regular, 0.8)),
+            FrameTemplate::Minimal => (FieldStyle::new(FontWeight::Regular, 0.9), FieldStyle::new(FontWeight::Light, 0.7)),
+            FrameTemplate::Bold => (FieldStyle::new(FontWeight::Bold, 1.2), FieldStyle::new(FontWeight::Regular, 0.8)),
+        };
+        FrameField::ALL
+            .iter()
+            .map(|field| if *field == FrameField::Model { model.clone() } else { params.clone() })
+            .collect()
+    }
+}
+
+// 画框中的一段文字（坐标为导出图片的像素坐标，锚点为文字中心）
+struct FrameTextItem {
+    text: String,
+    anchor: (f32, f32),
+    style: TextStyle,
+    fonts: Vec<FontSource>,
+}
+
+// 画框文字区域的排版结果，预览与导出共用
+struct FrameTextLayout {
+    area_y: f32,      // 文字区域上沿
+    area_height: f32, // 文字区域高度
+    items: Vec<FrameTextItem>,
+    swatches: Vec<(egui::Rect, egui::Color32)>, // 色板条色块
+    histogram: Option<(egui::Rect, Vec<f32>, egui::Color32)>, // 迷你直方图的范围、各柱高度和颜色
+}
+
+// 调色板颜色数量
+const PALETTE_SIZE: usize = 5;
+// 画框中迷你直方图的柱数量和不透明度
+const FRAME_HISTOGRAM_BARS: usize = 64;
+const FRAME_HISTOGRAM_OPACITY: f32 = 0.8;
+
+// 字体选择在 eframe 存储中的键名
+const FONT_SETTINGS_KEY: &str = "font_settings";
+// 会话状态在 eframe 存储中的键名
+const SESSION_KEY: &str = "session";
+// 最近打开文件列表的最大长度
+const MAX_RECENT_FILES: usize = 10;
+
+// 会话状态：画框设置、最近打开的文件和上次导出目录，退出时保存，启动时恢复
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct SessionState {
+    frame: FrameSettings,
+    recent_files: Vec<String>,
+    last_export_dir: Option<std::path::PathBuf>,
+    preserve_icc_profile: bool,
+    #[serde(default = "presets::builtin_presets")]
+    presets: Vec<Preset>,
+}
+
+/**
+ * 计算色板条布局
+ * @param area_height - 文字区域高度
+ * @param count - 色块数量
+ * @return (色块边长, 色块间距, 色板条总宽度)
+ */
+fn palette_strip_layout(area_height: f32, count: usize) -> (f32, f32, f32) {
+    let swatch = area_height * 0.35;
+    let gap = swatch * 0.25;
+    let total = if count > 0 { swatch * count as f32 + gap * (count - 1) as f32 } else { 0.0 };
+    (swatch, gap, total)
+}
+
+// 四边边框的像素值（相对于原图分辨率）
+#[derive(Clone, Copy, Default)]
+struct Borders {
+    up: u32,
+    down: u32,
+    left: u32,
+    right: u32,
+}
+
+// 画框合成所需的全部参数（按原图分辨率计算），导出和精确预览共用；
+// 不引用界面状态，可以发送到后台线程渲染
+struct FrameComposition {
+    borders: Borders,
+    fill: compose::BackgroundFill,
+    corner_radius: f32,
+    shadow: Option<(f32, f32, f32, f32)>, // 投影 (偏移x, 偏移y, 模糊半径, 不透明度)，未启用时为 None
+    layout: FrameTextLayout,
+}
+
+impl FrameComposition {
+    /**
+     * 合成画框图片：背景、投影、圆角照片、色板条和文字
+     * @param photo - 照片像素（导出时为原图，精确预览时为按 scale 缩小的原图）
+     * @param scale - 照片相对原图的缩放比例，边框、圆角、投影和文字同比缩放
+     * @return 与照片位深一致的画框图片
+     */
+    fn render(&self, photo: &image::DynamicImage, scale: f32) -> image::DynamicImage {
+        let scaled = |value: u32| (value as f32 * scale).round() as u32;
+        let (left, up) = (scaled(self.borders.left), scaled(self.borders.up));
+        let bg_width = photo.width() + left + scaled(self.borders.right);
+        let bg_height = photo.height() + up + scaled(self.borders.down);
+
+        // 创建匹配原图片位深的背景（纯色、渐变、主色或纹理）
+        let mut bg_image = compose::create_background(photo, bg_width, bg_height, &self.fill, scale);
+
+        // 先在背景上绘制投影，再叠加圆角照片（保留原始像素）
+        let corner_radius = self.corner_radius * scale;
+        if let Some((dx, dy, blur, opacity)) = self.shadow {
+            let shadow = compose::shadow_mask(
+                left as i64 + (dx * scale).round() as i64,
+                up as i64 + (dy * scale).round() as i64,
+                photo.width(),
+                photo.height(),
+                corner_radius,
+                blur * scale,
+            );
+            compose::fill_mask(&mut bg_image, &shadow, [0.0, 0.0, 0.0], opacity);
+        }
+        self.overlay_photo(&mut bg_image, photo, scale);
+
+        // 绘制色板条和画框文字（与快速预览共用同一套排版）
+        for (rect, color) in &self.layout.swatches {
+            compose::fill_rect(
+                &mut bg_image,
+                (rect.min.x * scale).round() as i64,
+                (rect.min.y * scale).round() as i64,
+                (rect.width() * scale).round() as u32,
+                (rect.height() * scale).round() as u32,
+                [color.r() as f32 / 255.0, color.g() as f32 / 255.0, color.b() as f32 / 255.0],
+            );
+        }
+        if let Some((rect, heights, color)) = &self.layout.histogram {
+            let mask = compose::bars_mask(rect.min.x * scale, rect.min.y * scale, rect.width() * scale, rect.height() * scale, heights);
+            let rgb = [color.r() as f32 / 255.0, color.g() as f32 / 255.0, color.b() as f32 / 255.0];
+            compose::fill_mask(&mut bg_image, &mask, rgb, FRAME_HISTOGRAM_OPACITY);
+        }
+        for item in &self.layout.items {
+            let style = TextStyle {
+                size: item.style.size * scale,
+                letter_spacing: item.style.letter_spacing * scale,
+                ..item.style
+            };
+            let anchor = (item.anchor.0 * scale, item.anchor.1 * scale);
+            text_render::render_text(&mut bg_image, &item.fonts, &item.text, anchor, &style);
+        }
+        bg_image
+    }
+
+    /**
+     * 把圆角照片叠加到画框的照片位置
+     * @param bg_image - 画框图片
+     * @param photo - 照片像素（尺寸与合成时一致）
+     * @param scale - 照片相对原图的缩放比例
+     */
+    fn overlay_photo(&self, bg_image: &mut image::DynamicImage, photo: &image::DynamicImage, scale: f32) {
+        let left = (self.borders.left as f32 * scale).round() as u32;
+        let up = (self.borders.up as f32 * scale).round() as u32;
+        compose::overlay_rounded(bg_image, photo, left, up, self.corner_radius * scale);
+    }
 }
 
 #[derive(Default)]
@@ -56,7 +805,7 @@ struct TextOptions {
     align: &'static str,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 struct TableRow {
     tag_name: String,
     tag_value: String,
@@ -113,27 +862,36 @@ impl Default for MyEguiApp {
             },
         ];
         Self {
-            selected_file: None,
-            texture: None,
-            image_size: egui::Vec2::new(0.0, 0.0),
+            doc: ImageDoc::blank(0, default_rows.clone(), None), // 使用默认行初始化
+            docs: HashMap::new(),
+            dock_state: DockState::new(Vec::new()).with_translations(dock_translations()),
+            next_doc_id: 1,
+            filmstrip: Filmstrip::default(),
+            pending_load: None,
+            load_queue: VecDeque::new(),
+            max_texture_side: 2048,
+            show_loupe: false,
+            accurate_preview: false,
+            histogram_luma: false,
+            crop_mode: false,
+            crop_ratio: CropRatio::Free,
+            crop_ratio_portrait: false,
             left_panel_width: 0.0,
-            rows: default_rows.clone(), // 使用默认行初始化
+            history_label: None,
             default_rows, // 保存备份
-            initial_exif_data: Vec::new(), // 初始为空向量
 
-            up_value: 0,
-            down_value: 75,
-            left_value: 0,
-            right_value: 0,
-            min_value: 0.0,
-            max_value: 100.0,
-            step: 1.0,
-            decimal_places: 1,
-            bg_color: egui::Color32::from_rgba_premultiplied(255, 255, 255, 255), // 默认白色背景
-            enable_blur_bg: false, // 默认不启用模糊背景
-            blur_strength: 1.0, // 默认模糊强度
+            recent_files: Vec::new(),
+            last_export_dir: None,
+            clipboard: None,
+            preserve_icc_profile: false,
+            presets: presets::builtin_presets(),
+            selected_preset: None,
+            preset_name: String::new(),
+
+            font_library: FontLibrary::load_system(),
+            ui_font_family: None, // 默认使用中文回退字体
+            export_font_family: None,
             show_custom_bg_color_picker: false, // 默认不显示自定义背景颜色选择器
-            original_image: None,
             export_toast: None,
             export_toast_is_success: false,
             toast_timer: None,
@@ -144,39 +902,25 @@ impl Default for MyEguiApp {
 /**
  * 设置 EGUI 字体的函数
  * @param ctx - EGUI 上下文引用，用于设置字体
+ * @param library - 字体库
+ * @param ui_font - 界面字体族，None 时使用默认中文字体
  */
-fn setup_fonts_and_style(ctx: &egui::Context) {
+fn setup_fonts_and_style(ctx: &egui::Context, library: &FontLibrary, ui_font: Option<&str>) {
     // 创建一个新的字体定义，使用默认配置
     let mut fonts = FontDefinitions::default();
 
-    // 从系统字体加载中文字体（这里以 "微软雅黑" 为例），将字体文件数据插入到字体定义中
-    fonts.font_data.insert(
-        "微软雅黑".to_owned(), // 字体名称
-        std::sync::Arc::new(
-            FontData::from_static(
-                include_bytes!(
-                    "C:\\Windows\\Fonts\\msyh.ttc" // 替换为你的字体路径
-                )
-            )
-        ), 
-    );
-    fonts.font_data.insert(
-        "Raleway_ExtraBold_Italic".to_owned(), // 字体名称
-        std::sync::Arc::new(
-            FontData::from_static(
-                include_bytes!(
-                    "C:\\Windows\\Fonts\\Raleway-Italic-VariableFont_wght.ttf"
-                )
-            )
-        ), 
-    );
-
-    // 将中文字体添加到默认字体族中
-    fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap()
-        .insert(0, "微软雅黑".to_owned());
-   
-    fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap()
-        .insert(1, "Raleway_ExtraBold_Italic".to_owned());
+    // 将界面字体及回退链（中文、英文回退字体，最后是内置字体）插入到字体定义中，
+    // 放在默认字体之前（英文符号和表情仍可回退到 egui 自带字体）
+    for (i, source) in library.chain(ui_font, None, FontWeight::Regular).into_iter().enumerate() {
+        let name = format!("界面字体{}", i);
+        let mut font_data = FontData::from_owned(source.data.as_ref().clone());
+        font_data.index = source.index;
+        fonts.font_data.insert(name.clone(), std::sync::Arc::new(font_data));
+        fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap()
+            .insert(i, name.clone());
+        fonts.families.get_mut(&egui::FontFamily::Monospace).unwrap()
+            .push(name);
+    }
 
     ctx.set_fonts(fonts); // 应用新的字体
 
@@ -260,6 +1004,34 @@ fn custom_text(
     }).inner;
 }
 
+/**
+ * 字体族下拉框
+ * @param ui - UI 上下文
+ * @param id_salt - 下拉框的唯一标识
+ * @param value - 选中的字体族，None 表示使用默认字体
+ * @param families - 可选的字体族
+ * @param default_label - None 选项显示的文字
+ * @return 选择是否发生了变化
+ */
+fn font_family_combo(
+    ui: &mut egui::Ui,
+    id_salt: impl std::hash::Hash,
+    value: &mut Option<String>,
+    families: &[String],
+    default_label: &str,
+) -> bool {
+    let before = value.clone();
+    ComboBox::from_id_salt(id_salt)
+        .selected_text(value.as_deref().unwrap_or(default_label))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(value, None, default_label);
+            for family in families {
+                ui.selectable_value(value, Some(family.clone()), family);
+            }
+        });
+    *value != before
+}
+
 
 fn analyse_jpg(exif: &Exif, row: &mut TableRow) {
    match row.tag_name.as_str() {
@@ -709,263 +1481,442 @@ fn update_row_from_xmp(row: &mut TableRow, xmp_data: &HashMap<String, String>) {
     }
 }
 
-impl MyEguiApp {
-    fn load_image(&mut self, ctx: &egui::Context, path: &str) -> Result<(), String> {
-
-        let start_time = std::time::Instant::now();  // 开始计时
-
-        // 读取图片文件到字节数组
-        // 使用std::fs::read读取整个文件到内存
-        let image_bytes = std::fs::read(path)
-            .map_err(|e| format!("无法读取文件: {}", e))?;
-        
-        // ========== 新增：保存原始图片数据（保留位深/像素） ==========
-        let original_image = image::load_from_memory(&image_bytes)
-            .map_err(|e| format!("无法解码图片: {}", e))?;
-        self.original_image = Some(original_image.clone());
-        
-        // 转换为EGUI显示用的Rgba8格式
-        // let image = original_image.to_rgba8();
-        // ========================================================
-
-        // 解码图片字节数据
-        // 使用image库从内存字节加载图片，支持多种格式(PNG, JPG, JPEG, BMP, GIF等)
-        let image = image::load_from_memory(&image_bytes)
-            .map_err(|e| format!("无法解码图片: {}", e))?
-            .to_rgba8();
-        
-        // 获取图片尺寸
-        // image.width()和image.height()返回u32类型，转换为usize用于数组索引
-        let size = [image.width() as _, image.height() as _];
-
-        // 创建EGUI颜色图像数据
-        // from_rgba_unmultiplied: 从非预乘RGBA数据创建颜色图像
-        // 预乘alpha意味着颜色值已经乘以了alpha值，这里使用非预乘格式
-        let image_data = ColorImage::from_rgba_unmultiplied(size, &image);
-        
-        // 存储图片尺寸到应用状态中，用于后续显示比例计算
-        // 转换为f32类型，因为EGUI使用浮点数坐标系统
-        self.image_size = egui::Vec2::new(size[0] as f32, size[1] as f32);
-        
-        // 从文件路径提取文件名作为纹理名称
-        // 如果无法提取文件名，使用默认名称"image"
-        let texture_name = Path::new(path)
-            .file_name() // 获取文件名部分(不含路径)
-            .and_then(|n| n.to_str()) // OsStr转换为&str
-            .unwrap_or("image"); // 如果转换失败使用默认值
-
-        self.texture = Some(ctx.load_texture(
-            texture_name, // 纹理标识名称
-            image_data, // 图片数据
-            TextureOptions::default() // 纹理选项
-        ));
-
-        self.read_exif(path);
-
-        // 如果是第一次加载图片，保存初始EXIF数据
-        if self.initial_exif_data.is_empty() {
-            self.initial_exif_data = self.rows.clone();
-        }
-
-        let duration = start_time.elapsed();  // 计算耗时
-        println!("图片加载耗时: {:?}", duration);  // 打印加载时间
-
-        Ok(())
-    }
-
-    // ========== 新增：文字绘制辅助函数 ==========
-    /// 将文字绘制到image::DynamicImage缓冲区
-    fn draw_text_to_image(
-        &self,
-        image: &mut image::DynamicImage,
-        font: &Font,
-        scale: Scale,
-        text: &str,
-        pos: (f32, f32),
-        text_rgb: (u8, u8, u8),
-    ) -> Result<(), String> {
-        // 转换为RGBA8格式（便于像素操作）
-        let mut img_buf = image.to_rgba8();
-        let (img_width, img_height) = (img_buf.width(), img_buf.height());
-
-        // 计算文字边界（居中对齐）
-        let v_metrics = font.v_metrics(scale);
-        let glyphs: Vec<_> = font.layout(text, scale, point(0.0, 0.0)).collect();
-        let text_width = glyphs.iter()
-            .rev()
-            .find_map(|g| g.pixel_bounding_box().map(|b| b.max.x))
-            .unwrap_or(0) as f32;
-        let text_height = (v_metrics.ascent - v_metrics.descent) as f32;
-
-        // 最终文字位置（居中）
-        let x = pos.0 - text_width / 2.0;
-        let y = pos.1 + text_height / 2.0 - v_metrics.descent;
-
-        // 逐字符绘制像素
-        for glyph in font.layout(text, scale, point(x, y)) {
-            if let Some(bounding_box) = glyph.pixel_bounding_box() {
-                glyph.draw(|gx, gy, alpha| {
-                    let px = bounding_box.min.x + gx as i32;
-                    let py = bounding_box.min.y + gy as i32;
-                    // 确保像素在图片范围内
-                    if px >= 0 && px < img_width as i32 && py >= 0 && py < img_height as i32 {
-                        let pixel = img_buf.get_pixel_mut(px as u32, py as u32);
-                        // 文字颜色混合（Alpha通道）
-                        let alpha = alpha as f32;
-                        pixel.0[0] = ((1.0 - alpha) * pixel.0[0] as f32 + alpha * text_rgb.0 as f32) as u8;
-                        pixel.0[1] = ((1.0 - alpha) * pixel.0[1] as f32 + alpha * text_rgb.1 as f32) as u8;
-                        pixel.0[2] = ((1.0 - alpha) * pixel.0[2] as f32 + alpha * text_rgb.2 as f32) as u8;
+/**
+ * 读取图片的 EXIF 信息，填入表格（在加载线程中调用）
+ * @param path - 图片路径
+ * @param rows - 空白表格
+ * @return 填好的表格，读取失败时第一行为错误信息
+ */
+fn read_exif(path: &str, mut rows: Vec<TableRow>) -> Vec<TableRow> {
+    let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).map(|s| s.to_lowercase());
+
+    match File::open(path) {
+        Ok(file) => {
+            match extension.as_deref() {
+                Some("jpg") | Some("jpeg") => {
+                    let mut buf_reader = BufReader::new(&file);
+                    match Reader::new().read_from_container(&mut buf_reader) {
+                        Ok(exif) => {
+                            for row in &mut rows {
+                                analyse_jpg(&exif, row);
+                            }
+                        }
+                        Err(e) => {
+                            eprintln!("无法读取JPEG EXIF数据: {}", e);
+                            if let Some(first_row) = rows.first_mut() {
+                                first_row.tag_value = format!("无法读取EXIF数据: {}", e);
+                            }
+                        }
                     }
-                });
+                }
+                Some("png") => {
+                    // 对于 PNG，只解析一次文件，然后使用数据更新所有行
+                    if let Ok(mut file) = File::open(path) {
+                        let mut buf_reader = BufReader::new(&mut file);
+                        match get_png_exif(&mut buf_reader) {
+                            Ok((exif_data, dimensions, xmp_exif_data)) => {
+                                // 将 Vec<u8> 包装为 Option<Vec<u8>>
+                                let exif_data_opt = if exif_data.is_empty() {
+                                    None
+                                } else {
+                                    Some(exif_data)
+                                };
+                                
+                                // 使用获取的数据更新所有行
+                                for row in &mut rows {
+                                    analyse_png(row, &exif_data_opt, &dimensions, &xmp_exif_data);
+                                }
+                            }
+                            Err(e) => {
+                                eprintln!("PNG EXIF extraction error: {}", e);
+                                for row in &mut rows {
+                                    if row.tag_value.is_empty() {
+                                        row.tag_value = "无EXIF数据".to_string();
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    // 对于其他格式，显示不支持的信息
+                    if let Some(first_row) = rows.first_mut() {
+                        first_row.tag_value = "不支持该格式的EXIF读取".to_string();
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("无法打开文件: {}", e);
+            if let Some(first_row) = rows.first_mut() {
+                first_row.tag_value = format!("无法打开文件: {}", e);
             }
         }
-
-        // 将修改后的缓冲区写回原图片
-        *image = image::DynamicImage::ImageRgba8(img_buf);
-        Ok(())
     }
+    rows
+}
 
-    // ========== 完整的导出图片函数（包含文字绘制） ==========
-    fn export_combined_image(&self) -> Result<(), String> {
-        // 检查必要条件
-        let selected_path = self.selected_file.as_ref()
-            .ok_or("未选择图片文件")?;
-        let original_image = self.original_image.as_ref()
-            .ok_or("未加载原始图片数据")?;
-
-        let original_width = original_image.width();
-        let original_height = original_image.height();
-
-        // 计算新图片尺寸（原图片尺寸 + 偏移值）
-        let bg_width = original_width + self.left_value as u32 + self.right_value as u32;
-        let bg_height = original_height + self.up_value as u32 + self.down_value as u32;
-
-        // 创建匹配原图片位深的背景
-        let mut bg_image: image::DynamicImage = match original_image {
-            image::DynamicImage::ImageRgba8(_) => {
-                let bg_buf = image::ImageBuffer::from_pixel(bg_width, bg_height, image::Rgba([
-                    self.bg_color.r(), 
-                    self.bg_color.g(), 
-                    self.bg_color.b(), 
-                    self.bg_color.a()
-                ]));
-                image::DynamicImage::ImageRgba8(bg_buf)
-            }
-            image::DynamicImage::ImageRgb8(_) => {
-                let bg_buf = image::ImageBuffer::from_pixel(bg_width, bg_height, image::Rgb([
-                    self.bg_color.r(), 
-                    self.bg_color.g(), 
-                    self.bg_color.b()
-                ]));
-                image::DynamicImage::ImageRgb8(bg_buf)
+/**
+ * 把预览的渲染比例按半个八度向上取整：缩放时比例在同一档内变化不必重新生成纹理
+ * @param scale - 每个导出像素对应的屏幕物理像素数
+ */
+fn quantize_preview_scale(scale: f32) -> f32 {
+    2f32.powf((scale.log2() * 2.0).ceil() / 2.0)
+}
+
+/**
+ * 光标位置对应的裁剪框拖动方式：靠近边或角时调整大小，框内为平移，框外为 None
+ * @param rect - 裁剪框在屏幕上的范围
+ */
+fn crop_handle_at(rect: egui::Rect, pos: egui::Pos2) -> Option<CropHandle> {
+    if !rect.expand(CROP_HANDLE_SIZE).contains(pos) {
+        return None;
+    }
+    let side = |value: f32, min: f32, max: f32| {
+        if (value - min).abs() <= CROP_HANDLE_SIZE {
+            -1
+        } else if (value - max).abs() <= CROP_HANDLE_SIZE {
+            1
+        } else {
+            0
+        }
+    };
+    let horizontal = side(pos.x, rect.left(), rect.right());
+    let vertical = side(pos.y, rect.top(), rect.bottom());
+    if horizontal == 0 && vertical == 0 {
+        return rect.contains(pos).then_some(CropHandle::Move);
+    }
+    Some(CropHandle::Resize { horizontal, vertical })
+}
+
+/**
+ * 生成按几何调整绘制照片的网格：顶点位于照片轮廓（圆角时为圆角矩形）上，
+ * 纹理坐标由调整参数换算回原图；换算是仿射变换，三角形内部插值同样准确
+ * @param source_size - 原图尺寸
+ * @param rect - 调整后的照片在屏幕上的范围
+ * @param radius - 屏幕上的圆角半径（点）
+ */
+fn geometry_mesh(texture_id: egui::TextureId, source_size: (u32, u32), geometry: &Geometry, rect: egui::Rect, radius: f32) -> egui::Mesh {
+    let (output_width, _) = geometry.output_size(source_size.0, source_size.1);
+    let scale = rect.width() / output_width as f32;
+    let radius = radius.min(rect.width().min(rect.height()) / 2.0).max(0.0);
+    // 每段圆弧约 4 点长，大圆角也保持平滑
+    let segments = ((radius / 4.0).ceil() as usize).clamp(8, 128);
+
+    // 圆角矩形轮廓（顺时针），没有圆角时为四个角点
+    let mut outline = Vec::new();
+    let corners = [
+        (rect.right_top() + egui::vec2(-radius, radius), -90.0),
+        (rect.right_bottom() + egui::vec2(-radius, -radius), 0.0),
+        (rect.left_bottom() + egui::vec2(radius, -radius), 90.0),
+        (rect.left_top() + egui::vec2(radius, radius), 180.0),
+    ];
+    for (center, start) in corners {
+        if radius <= 0.0 {
+            outline.push(center);
+            continue;
+        }
+        for i in 0..=segments {
+            let angle = (start + 90.0 * i as f32 / segments as f32).to_radians();
+            outline.push(center + egui::vec2(angle.cos(), angle.sin()) * radius);
+        }
+    }
+
+    let mut mesh = egui::Mesh::with_texture(texture_id);
+    let mut add_vertex = |pos: egui::Pos2| {
+        let local = (pos - rect.min) / scale;
+        let (x, y) = geometry.source_point(source_size.0, source_size.1, (local.x, local.y));
+        let uv = egui::pos2(x / source_size.0 as f32, y / source_size.1 as f32);
+        mesh.vertices.push(egui::epaint::Vertex { pos, uv, color: egui::Color32::WHITE });
+    };
+    add_vertex(rect.center());
+    for &pos in &outline {
+        add_vertex(pos);
+    }
+    let count = outline.len() as u32;
+    for i in 0..count {
+        mesh.add_triangle(0, 1 + i, 1 + (i + 1) % count);
+    }
+    mesh
+}
+
+/**
+ * 按目标宽高比计算画布边框
+ * @param width, height - 原图尺寸
+ * @param ratio - 目标宽高比（宽, 高）
+ * @param margin_percent - 最小留白，占原图长边的百分比
+ * @param offset - 图片在剩余空间中的偏移，(-1, -1) 为左上，(0, 0) 为居中，(1, 1) 为右下
+ */
+fn compute_canvas_borders(
+    width: u32,
+    height: u32,
+    ratio: (u32, u32),
+    margin_percent: f32,
+    offset: (f32, f32),
+) -> Borders {
+    let long_edge = width.max(height) as f64;
+    let margin = (long_edge * margin_percent.max(0.0) as f64 / 100.0).round();
+
+    // 先加上最小留白，得到最小画布
+    let min_width = width as f64 + margin * 2.0;
+    let min_height = height as f64 + margin * 2.0;
+
+    // 再把较短的一边扩展到目标比例
+    let target = ratio.0.max(1) as f64 / ratio.1.max(1) as f64;
+    let (canvas_width, canvas_height) = if min_width / min_height < target {
+        ((min_height * target).ceil(), min_height)
+    } else {
+        (min_width, (min_width / target).ceil())
+    };
+
+    // 多出来的空间按偏移量分配到两侧，两侧都至少保留 margin
+    let free_x = canvas_width - min_width;
+    let free_y = canvas_height - min_height;
+    let left = margin + (free_x * (offset.0.clamp(-1.0, 1.0) as f64 + 1.0) / 2.0).round();
+    let up = margin + (free_y * (offset.1.clamp(-1.0, 1.0) as f64 + 1.0) / 2.0).round();
+
+    Borders {
+        up: up as u32,
+        down: (canvas_height - height as f64 - up) as u32,
+        left: left as u32,
+        right: (canvas_width - width as f64 - left) as u32,
+    }
+}
+
+impl MyEguiApp {
+    // 根据当前边框模式计算四边边框（以原图像素为单位），预览和导出共用
+    fn frame_borders(&self, width: u32, height: u32) -> Borders {
+        match self.doc.frame.border_mode {
+            BorderMode::Pixel => Borders {
+                up: self.doc.frame.up_value,
+                down: self.doc.frame.down_value,
+                left: self.doc.frame.left_value,
+                right: self.doc.frame.right_value,
+            },
+            BorderMode::Percent => {
+                let base = match self.doc.frame.percent_base {
+                    PercentBase::ShortEdge => width.min(height),
+                    PercentBase::LongEdge => width.max(height),
+                } as f32;
+                let to_pixels = |percent: f32| (base * percent.max(0.0) / 100.0).round() as u32;
+                Borders {
+                    up: to_pixels(self.doc.frame.up_percent),
+                    down: to_pixels(self.doc.frame.down_percent),
+                    left: to_pixels(self.doc.frame.left_percent),
+                    right: to_pixels(self.doc.frame.right_percent),
+                }
             }
-            image::DynamicImage::ImageRgba16(_) => {
-                let bg_buf = image::ImageBuffer::from_pixel(bg_width, bg_height, image::Rgba([
-                    self.bg_color.r() as u16 * 257, 
-                    self.bg_color.g() as u16 * 257, 
-                    self.bg_color.b() as u16 * 257, 
-                    self.bg_color.a() as u16 * 257
-                ]));
-                image::DynamicImage::ImageRgba16(bg_buf)
+            BorderMode::AspectRatio => {
+                let (w, h) = self.doc.frame.canvas_ratio.ratio();
+                let ratio = if self.doc.frame.canvas_ratio_swapped { (h, w) } else { (w, h) };
+                compute_canvas_borders(
+                    width,
+                    height,
+                    ratio,
+                    self.doc.frame.canvas_margin_percent,
+                    (self.doc.frame.canvas_offset_x, self.doc.frame.canvas_offset_y),
+                )
             }
-            _ => {
-                let bg_buf = image::ImageBuffer::from_pixel(bg_width, bg_height, image::Rgba([255, 255, 255, 255]));
-                image::DynamicImage::ImageRgba8(bg_buf)
+        }
+    }
+
+    // 当前背景设置对应的填充方式，预览和导出共用
+    fn background_fill(&self) -> compose::BackgroundFill {
+        let rgba = |c: egui::Color32| [c.r(), c.g(), c.b(), c.a()];
+        match self.doc.frame.bg_kind {
+            BackgroundKind::Solid => compose::BackgroundFill::Solid(rgba(self.doc.frame.bg_color)),
+            BackgroundKind::LinearGradient => compose::BackgroundFill::LinearGradient {
+                from: rgba(self.doc.frame.bg_color),
+                to: rgba(self.doc.frame.bg_gradient_end),
+                angle_degrees: self.doc.frame.bg_gradient_angle,
+            },
+            BackgroundKind::RadialGradient => compose::BackgroundFill::RadialGradient {
+                from: rgba(self.doc.frame.bg_color),
+                to: rgba(self.doc.frame.bg_gradient_end),
+            },
+            BackgroundKind::DominantColor => {
+                compose::BackgroundFill::Solid(rgba(self.doc.dominant_color.unwrap_or(self.doc.frame.bg_color)))
             }
-        };
+            BackgroundKind::Texture => match &self.doc.bg_texture {
+                Some(texture) => compose::BackgroundFill::Texture { image: texture.clone(), fit: self.doc.frame.bg_texture_fit },
+                None => compose::BackgroundFill::Solid(rgba(self.doc.frame.bg_color)),
+            },
+        }
+    }
 
-        // 将原图片按偏移位置叠加到背景（保留原始像素）
-        let offset_x = self.left_value as i64;
-        let offset_y = self.up_value as i64;
-        image::imageops::overlay(
-            &mut bg_image,
-            original_image,
-            offset_x as i64,
-            offset_y as i64,
-        );
+    // 背景的代表色，用于计算与之对比的文字颜色
+    fn effective_bg_color(&self) -> egui::Color32 {
+        match self.doc.frame.bg_kind {
+            BackgroundKind::Solid => self.doc.frame.bg_color,
+            BackgroundKind::LinearGradient | BackgroundKind::RadialGradient => {
+                egui::Color32::from_rgb(
+                    ((self.doc.frame.bg_color.r() as u16 + self.doc.frame.bg_gradient_end.r() as u16) / 2) as u8,
+                    ((self.doc.frame.bg_color.g() as u16 + self.doc.frame.bg_gradient_end.g() as u16) / 2) as u8,
+                    ((self.doc.frame.bg_color.b() as u16 + self.doc.frame.bg_gradient_end.b() as u16) / 2) as u8,
+                )
+            }
+            BackgroundKind::DominantColor => self.doc.dominant_color.unwrap_or(self.doc.frame.bg_color),
+            BackgroundKind::Texture => self.doc.bg_texture_color.unwrap_or(self.doc.frame.bg_color),
+        }
+    }
 
-        // 准备要绘制的EXIF文字信息
-        let mut text_lines = Vec::new();
-        if let Some(camera_model) = self.rows.iter().find(|r| r.tag_name == "相机型号") {
-            text_lines.push(format!("{}", camera_model.tag_value));
+    // 文字颜色：优先使用自定义颜色，否则与背景形成对比（浅色背景用黑字，深色背景用白字）
+    fn resolved_text_color(&self) -> egui::Color32 {
+        if let Some(color) = self.doc.frame.text_color {
+            return color;
         }
-        if let Some(iso) = self.rows.iter().find(|r| r.tag_name == "ISO") {
-            text_lines.push(format!("ISO{}", iso.tag_value));
+        let (r, g, b, _) = self.effective_bg_color().to_tuple();
+        let brightness = (r as f32 * 0.299 + g as f32 * 0.587 + b as f32 * 0.114) / 255.0;
+        if brightness > 0.5 {
+            egui::Color32::BLACK
+        } else {
+            egui::Color32::WHITE
         }
-        if let Some(aperture) = self.rows.iter().find(|r| r.tag_name == "光圈") {
-            text_lines.push(format!("{}", aperture.tag_value));
+    }
+
+    // 加载纹理背景图片
+    fn load_bg_texture(&mut self, path: &str) -> Result<(), String> {
+        let texture = image::open(path)
+            .map_err(|e| format!("无法加载纹理图片: {}", e))?
+            .to_rgba8();
+        let [r, g, b] = compose::average_color(&texture);
+        self.doc.bg_texture_color = Some(egui::Color32::from_rgb(r, g, b));
+        self.doc.bg_texture = Some(Arc::new(texture));
+        self.doc.frame.bg_texture_path = Some(path.to_string());
+        Ok(())
+    }
+
+    // 非纯色背景在预览中使用同一套填充逻辑生成的小纹理，参数不变时复用
+    fn update_bg_preview_texture(&mut self, ctx: &egui::Context) {
+        if self.doc.frame.bg_kind == BackgroundKind::Solid || self.doc.frame.bg_kind == BackgroundKind::DominantColor || self.doc.texture.is_none() {
+            self.doc.bg_preview_texture = None;
+            return;
         }
-        if let Some(exposure) = self.rows.iter().find(|r| r.tag_name == "曝光时长") {
-            text_lines.push(format!("{}", exposure.tag_value));
+
+        let borders = self.frame_borders(self.doc.image_size.x as u32, self.doc.image_size.y as u32);
+        let full_width = self.doc.image_size.x + (borders.left + borders.right) as f32;
+        let full_height = self.doc.image_size.y + (borders.up + borders.down) as f32;
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        self.doc.frame.bg_kind.hash(&mut hasher);
+        self.doc.frame.bg_color.hash(&mut hasher);
+        self.doc.frame.bg_gradient_end.hash(&mut hasher);
+        self.doc.frame.bg_gradient_angle.to_bits().hash(&mut hasher);
+        self.doc.frame.bg_texture_path.hash(&mut hasher);
+        (self.doc.frame.bg_texture_fit == compose::TextureFit::Tile).hash(&mut hasher);
+        (full_width as u32, full_height as u32).hash(&mut hasher);
+        let key = hasher.finish();
+        if matches!(&self.doc.bg_preview_texture, Some((cached, _)) if *cached == key) {
+            return;
         }
-        if let Some(focal_length) = self.rows.iter().find(|r| r.tag_name == "焦距") {
-            text_lines.push(format!("{}", focal_length.tag_value));
+
+        // 预览纹理长边最多 512 像素，坐标换算回原图分辨率后采样
+        let preview_scale = (512.0 / full_width.max(full_height)).min(1.0);
+        let width = ((full_width * preview_scale).round() as usize).max(1);
+        let height = ((full_height * preview_scale).round() as usize).max(1);
+        let fill = self.background_fill();
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let c = fill.sample(
+                    (x as f32 + 0.5) / preview_scale,
+                    (y as f32 + 0.5) / preview_scale,
+                    full_width,
+                    full_height,
+                );
+                pixels.push(egui::Color32::from_rgba_unmultiplied(
+                    (c[0] * 255.0).round() as u8,
+                    (c[1] * 255.0).round() as u8,
+                    (c[2] * 255.0).round() as u8,
+                    (c[3] * 255.0).round() as u8,
+                ));
+            }
         }
+        let image = ColorImage { size: [width, height], source_size: egui::vec2(width as f32, height as f32), pixels };
+        let handle = ctx.load_texture("bg_preview", image, TextureOptions::LINEAR);
+        self.doc.bg_preview_texture = Some((key, handle));
+    }
+
+    // 圆角和投影参数换算为像素（以原图短边为参照）：(圆角半径, 投影偏移x, 投影偏移y, 模糊半径)
+    fn photo_effect_pixels(&self, width: u32, height: u32) -> (f32, f32, f32, f32) {
+        let short_edge = width.min(height) as f32;
+        let to_pixels = |percent: f32| short_edge * percent / 100.0;
+        (
+            to_pixels(self.doc.frame.corner_radius_percent.max(0.0)),
+            to_pixels(self.doc.frame.shadow_offset_x_percent),
+            to_pixels(self.doc.frame.shadow_offset_y_percent),
+            to_pixels(self.doc.frame.shadow_blur_percent.max(0.0)),
+        )
+    }
 
-        // 兜底：如果无EXIF字段，强制添加测试文字
-        if text_lines.is_empty() {
-            text_lines.push("测试文字1".to_string());
-            text_lines.push("测试文字2".to_string());
-            eprintln!("无EXIF字段，添加测试文字：{:?}", text_lines);
+    // 当前画框设置对应的合成参数（原图尺寸为 width x height）
+    fn frame_composition(&self, width: u32, height: u32) -> FrameComposition {
+        let (corner_radius, shadow_dx, shadow_dy, shadow_blur) = self.photo_effect_pixels(width, height);
+        let frame = &self.doc.frame;
+        FrameComposition {
+            borders: self.frame_borders(width, height),
+            fill: self.background_fill(),
+            corner_radius,
+            shadow: (frame.shadow_enabled && frame.shadow_opacity > 0.0)
+                .then_some((shadow_dx, shadow_dy, shadow_blur, frame.shadow_opacity)),
+            layout: self.frame_text_layout(width, height),
         }
+    }
 
-        // 计算文字颜色（与背景形成对比）
-        let (r, g, b, _) = self.bg_color.to_tuple();
-        let brightness = (r as f32 * 0.299 + g as f32 * 0.587 + b as f32 * 0.114) / 255.0;
-        let text_rgb = if brightness > 0.5 {
-            (0, 0, 0)  // 浅色背景用黑色文字
-        } else {
-            (255, 255, 255)  // 深色背景用白色文字
-        };
+    /**
+     * 按原图分辨率合成画框图片（导出和复制到剪贴板共用）
+     * @param preserve_icc - 是否保留照片原来的色彩配置文件（否则整张图片为 sRGB）
+     * @return (画框图片, 需要写入文件的配置文件数据)
+     */
+    fn compose_frame_image(&self, preserve_icc: bool) -> Result<(image::DynamicImage, Option<Vec<u8>>), String> {
+        let original_image = self.doc.original_image.as_ref()
+            .ok_or("未加载原始图片数据")?;
 
-        // 加载字体用于文字绘制（适配多系统）
-        let font_data = self.load_system_font()?;
-        let font = Font::try_from_bytes(&font_data)
-            .ok_or_else(|| format!("无法加载字体: 无效字体数据"))?;
+        if self.font_library.is_empty() {
+            return Err("系统未找到可用字体".to_string());
+        }
 
-        // 计算文字区域高度和位置
-        let text_area_height = (bg_height as f32 * 0.15) as u32; // 占背景高度的15%
-        let text_start_y = offset_y + original_height as i64 + (self.down_value as i64 - text_area_height as i64) / 2;
-        
-        // 检查文字区域是否有效
-        let (text_start_y, text_area_height) = if text_area_height == 0 || text_start_y < 0 {
-            let new_text_y = (bg_height - 50) as i64;
-            let new_text_h = 50;
-            eprintln!("文字区域无效，强制调整：y={}, 高度={}", new_text_y, new_text_h);
-            (new_text_y, new_text_h as u32)
+        // 对原图执行旋转、翻转、拉直和裁剪
+        let edited;
+        let photo = if self.doc.geometry.is_identity() {
+            &**original_image
         } else {
-            (text_start_y, text_area_height)
+            edited = self.doc.geometry.apply(original_image);
+            &edited
         };
-        
-        // 计算字体大小（根据文字区域高度动态调整）
-        let font_size = (text_area_height as f32 * 0.6) as f32;
-        let scale = Scale::uniform(font_size);
-
-        // 计算每行文字的位置并绘制
-        let total_text_width = bg_width - 40; // 减去边距
-        let text_spacing = if text_lines.len() > 0 {
-            total_text_width / text_lines.len() as u32
-        } else {
-            0
+
+        // 按原图分辨率合成（与精确预览使用同一套流程）；边框、文字等颜色按 sRGB 绘制，照片先转换为 sRGB
+        let composition = self.frame_composition(photo.width(), photo.height());
+        let icc_profile = self.doc.icc_profile.as_deref();
+        let bg_image = match icc_profile {
+            Some(icc) => {
+                let bg_image = composition.render(&icc.to_srgb(photo), 1.0);
+                if preserve_icc {
+                    // 保留原配置文件：整张画框转换回照片的色彩空间，再叠加未经转换的原图（不损失广色域）
+                    let mut bg_image = icc.srgb_to_source(&bg_image);
+                    composition.overlay_photo(&mut bg_image, photo, 1.0);
+                    bg_image
+                } else {
+                    bg_image
+                }
+            }
+            None => composition.render(photo, 1.0),
         };
-        
-        for (i, text) in text_lines.iter().enumerate() {
-            let x_pos = (offset_x + 20 + (i as i64 * text_spacing as i64) + text_spacing as i64 / 2) as f32;
-            let y_pos = text_start_y as f32 + text_area_height as f32 / 2.0;
-            
-            eprintln!("绘制文字「{}」，坐标({},{})", text, x_pos, y_pos);
-            self.draw_text_to_image(
-                &mut bg_image,
-                &font,
-                scale,
-                text,
-                (x_pos, y_pos),
-                text_rgb
-            )?;
+        // 导出文件内嵌的配置文件（转换为 sRGB 时不写入，按 sRGB 解读）
+        let embedded_icc = icc_profile.filter(|_| preserve_icc).map(|icc| icc.data().to_vec());
+        Ok((bg_image, embedded_icc))
+    }
+
+    // ========== 完整的导出图片函数（包含文字绘制） ==========
+    fn export_combined_image(&self) -> Result<std::path::PathBuf, String> {
+        // 检查必要条件
+        if self.doc.original_image.is_none() {
+            return Err("未选择图片文件".to_string());
         }
+        let (bg_image, embedded_icc) = self.compose_frame_image(self.preserve_icc_profile)?;
 
         // 处理文件名（添加_exif_frame后缀）
-        let original_path = std::path::PathBuf::from(selected_path);
+        let original_path = std::path::PathBuf::from(&self.doc.file_name);
         let original_filename = original_path.file_stem()
             .ok_or("无法获取文件名")?
             .to_str()
@@ -978,8 +1929,12 @@ impl MyEguiApp {
 
         // 弹出保存对话框
         let default_save_name = format!("{}_exif_frame.{}", original_filename, original_ext);
-        let save_path = rfd::FileDialog::new()
-            .set_file_name(&default_save_name)
+        let mut save_dialog = rfd::FileDialog::new()
+            .set_file_name(&default_save_name);
+        if let Some(dir) = &self.last_export_dir {
+            save_dialog = save_dialog.set_directory(dir);
+        }
+        let save_path = save_dialog
             .add_filter("支持的格式", &["jpg", "jpeg", "png"])
             .save_file()
             .ok_or("用户取消保存")?;
@@ -999,303 +1954,2313 @@ impl MyEguiApp {
             },
         };
 
-        // JPG兼容处理（JPG不支持16位）
-        let final_bg_image = if export_format == image::ImageFormat::Jpeg {
-            match &bg_image {
-                image::DynamicImage::ImageRgba16(img) => {
-                    image::DynamicImage::ImageRgba16(img.clone()).to_rgba8()
-                },
-                image::DynamicImage::ImageRgb16(img) => {
-                    image::DynamicImage::ImageRgb16(img.clone()).to_rgba8()
-                },
-                _ => bg_image.to_rgba8(),
-            }
-        } else {
-            bg_image.to_rgba8()
-        };
-
         // 保存最终图片
         let mut output_file = File::create(&save_path)
             .map_err(|e| format!("无法创建输出文件: {}", e))?;
-        
-        final_bg_image.write_to(&mut output_file, export_format)
-            .map_err(|e| format!("保存图片失败: {}", e))?;
 
+        // 写入原图的 EXIF（方向标记已在加载时重置为 1，与摆正后的像素一致；尺寸改为导出尺寸，去掉原图缩略图）和保留的配置文件
+        let export_exif = self.doc.exif_metadata
+            .as_deref()
+            .map(|exif| loader::export_exif(exif, bg_image.width(), bg_image.height()));
+        let write_result = loader::encode_export(&mut output_file, &bg_image, export_format, export_exif, embedded_icc);
+        write_result.map_err(|e| format!("保存图片失败: {}", e))?;
+
+        Ok(save_path)
+    }
+
+    // ========== 新增：剪贴板 ==========
+    // 系统剪贴板（首次使用时创建并一直保留：Linux 上剪贴板内容由该对象提供，释放后其他程序无法粘贴）
+    fn clipboard(&mut self) -> Result<&mut arboard::Clipboard, String> {
+        if self.clipboard.is_none() {
+            self.clipboard = Some(arboard::Clipboard::new().map_err(|e| format!("无法访问剪贴板: {}", e))?);
+        }
+        Ok(self.clipboard.as_mut().expect("剪贴板已创建"))
+    }
+
+    // 把合成的画框图片复制到剪贴板（按 sRGB 复制，聊天软件等不读取配置文件）
+    fn copy_image_to_clipboard(&mut self) -> Result<(), String> {
+        let (image, _) = self.compose_frame_image(false)?;
+        let rgba = image.to_rgba8();
+        let data = arboard::ImageData {
+            width: rgba.width() as usize,
+            height: rgba.height() as usize,
+            bytes: std::borrow::Cow::Owned(rgba.into_raw()),
+        };
+        self.clipboard()?.set_image(data).map_err(|e| format!("复制图片失败: {}", e))
+    }
+
+    // 把 EXIF 表格中有值的字段按「名称: 值」逐行复制到剪贴板
+    fn copy_exif_to_clipboard(&mut self) -> Result<(), String> {
+        let text = self.doc.rows
+            .iter()
+            .filter(|row| !row.tag_value.is_empty())
+            .map(|row| format!("{}: {}", row.tag_name, row.tag_value))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if text.is_empty() {
+            return Err("没有可复制的EXIF信息".to_string());
+        }
+        self.clipboard()?.set_text(text).map_err(|e| format!("复制文字失败: {}", e))
+    }
+
+    // 把剪贴板中的图片直接交给加载线程，在新标签页中打开（不保存文件，也不加入最近打开列表）
+    fn paste_image_from_clipboard(&mut self) -> Result<(), String> {
+        let data = self.clipboard()?.get_image().map_err(|e| format!("剪贴板中没有图片: {}", e))?;
+        let image = image::RgbaImage::from_raw(data.width as u32, data.height as u32, data.bytes.into_owned())
+            .ok_or("剪贴板图片数据无效")?;
+
+        // 粘贴的图片按 PNG 导出，名称带上粘贴时间以区分多次粘贴
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis())
+            .unwrap_or_default();
+        self.load_queue.clear();
+        self.pending_load = Some(PendingLoad::start_pixels(
+            format!("剪贴板_{}.png", timestamp),
+            image::DynamicImage::ImageRgba8(image),
+            LoadTarget::NewTab,
+            self.default_rows.clone(),
+            PALETTE_SIZE,
+            self.max_texture_side,
+        ));
         Ok(())
     }
 
-    // ========== 新增：多系统字体加载函数 ==========
-    fn load_system_font(&self) -> Result<Vec<u8>, String> {
-        // 根据操作系统选择字体路径
-        if cfg!(windows) {
-            // Windows 系统字体（宋体/微软雅黑）
-            let font_paths = [
-                "C:\\Windows\\Fonts\\simsun.ttc",    // 宋体
-                "C:\\Windows\\Fonts\\msyh.ttc",     // 微软雅黑
-                "C:\\Windows\\Fonts\\arial.ttf"     // 备用英文字体
-            ];
-            for path in font_paths {
-                if let Ok(data) = std::fs::read(path) {
-                    eprintln!("加载Windows字体成功: {}", path);
-                    return Ok(data);
+    // ========== 新增：画框文字排版（预览与导出共用） ==========
+    // 坐标均为导出图片（原图 + 边框）的像素坐标
+    fn frame_text_layout(&self, image_width: u32, image_height: u32) -> FrameTextLayout {
+        let borders = self.frame_borders(image_width, image_height);
+        let bg_width = (image_width + borders.left + borders.right) as f32;
+        let bg_height = (image_height + borders.up + borders.down) as f32;
+
+        // 文字区域高度占背景高度的15%，在下边框内垂直居中
+        let area_height = (bg_height * 0.15).floor();
+        let area_y = (borders.up + image_height) as f32 + ((borders.down as f32 - area_height) / 2.0).trunc();
+
+        // 检查文字区域是否有效
+        let (area_y, area_height) = if area_height <= 0.0 || area_y < 0.0 {
+            let new_text_y = (bg_height - 50.0).max(0.0);
+            eprintln!("文字区域无效，强制调整：y={}, 高度={}", new_text_y, 50);
+            (new_text_y, 50.0)
+        } else {
+            (area_y, area_height)
+        };
+
+        // 色板条位于文字区域右侧，并为文字让出相应宽度
+        let mut text_area_width = (bg_width - 40.0).max(0.0); // 减去边距
+        let mut swatches = Vec::new();
+        if self.doc.frame.show_palette_strip && !self.doc.palette.is_empty() {
+            let (swatch, gap, strip_width) = palette_strip_layout(area_height, self.doc.palette.len());
+            let strip_x = bg_width - 20.0 - strip_width;
+            let strip_y = area_y + (area_height - swatch) / 2.0;
+            for (i, color) in self.doc.palette.iter().enumerate() {
+                let min = egui::pos2(strip_x + i as f32 * (swatch + gap), strip_y);
+                swatches.push((egui::Rect::from_min_size(min, egui::vec2(swatch, swatch)), *color));
+            }
+            text_area_width = (text_area_width - strip_width - 20.0).max(0.0);
+        }
+
+        // 迷你直方图位于文字区域左侧（宽高比 2:1，与色板条同高的一半），同样为文字让出宽度
+        let mut text_x = 20.0;
+        let mut histogram = None;
+        if let (true, Some(stats)) = (self.doc.frame.show_histogram, &self.doc.histogram) {
+            let graph_height = area_height * 0.5;
+            let graph_width = graph_height * 2.0;
+            let min = egui::pos2(text_x, area_y + (area_height - graph_height) / 2.0);
+            let rect = egui::Rect::from_min_size(min, egui::vec2(graph_width, graph_height));
+            histogram = Some((rect, stats.heights(histogram::Channel::Luma, FRAME_HISTOGRAM_BARS), self.resolved_text_color()));
+            text_x += graph_width + 20.0;
+            text_area_width = (text_area_width - graph_width - 20.0).max(0.0);
+        }
+
+        // 准备要绘制的EXIF文字信息，每个字段使用各自的样式
+        let mut fields: Vec<(String, FieldStyle)> = FrameField::ALL
+            .iter()
+            .zip(&self.doc.frame.field_styles)
+            .filter_map(|(field, style)| {
+                let row = self.doc.rows.iter().find(|r| r.tag_name == field.tag_name())?;
+                Some((field.display_text(&row.tag_value), style.clone()))
+            })
+            .collect();
+
+        // 兜底：无数据时显示提示
+        if fields.is_empty() {
+            fields.push(("无EXIF信息".to_string(), FieldStyle::new(FontWeight::Regular, 1.0)));
+        }
+
+        let text_spacing = text_area_width / fields.len() as f32;
+        let base_color = self.resolved_text_color();
+        let items = fields
+            .into_iter()
+            .enumerate()
+            .map(|(i, (text, field_style))| {
+                let color = field_style.color.unwrap_or(base_color);
+                let style = TextStyle {
+                    size: area_height * 0.6 * field_style.size_ratio,
+                    color: color.to_srgba_unmultiplied(),
+                    h_align: text_render::HAlign::Center,
+                    v_align: text_render::VAlign::Middle,
+                    ..TextStyle::default()
+                };
+                FrameTextItem {
+                    text,
+                    anchor: (text_x + i as f32 * text_spacing + text_spacing / 2.0, area_y + area_height / 2.0),
+                    style,
+                    fonts: match &field_style.font_family {
+                        Some(family) => self.font_library.chain(Some(family), field_style.font_style.as_deref(), field_style.weight),
+                        None => self.font_library.chain(self.export_font_family.as_deref(), None, field_style.weight),
+                    },
+                }
+            })
+            .collect();
+
+        FrameTextLayout { area_y, area_height, items, swatches, histogram }
+    }
+
+    // 预览中的画框文字使用与导出相同的排版和光栅化，按预览分辨率生成纹理，参数不变时复用
+    fn update_text_preview_texture(&mut self, ctx: &egui::Context, layout: &FrameTextLayout, bg_width: f32, scale: f32) {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        scale.to_bits().hash(&mut hasher);
+        bg_width.to_bits().hash(&mut hasher);
+        (layout.area_y.to_bits(), layout.area_height.to_bits()).hash(&mut hasher);
+        for item in &layout.items {
+            item.text.hash(&mut hasher);
+            (item.anchor.0.to_bits(), item.anchor.1.to_bits()).hash(&mut hasher);
+            (item.style.size.to_bits(), item.style.color).hash(&mut hasher);
+            for font in &item.fonts {
+                (std::sync::Arc::as_ptr(&font.data) as usize, font.index).hash(&mut hasher);
+            }
+        }
+        let key = hasher.finish();
+        if matches!(&self.doc.text_preview_texture, Some((cached, _)) if *cached == key) {
+            return;
+        }
+
+        // 只生成文字区域这一条带，透明背景叠加在预览背景上
+        let width = ((bg_width * scale).ceil() as u32).max(1);
+        let height = ((layout.area_height * scale).ceil() as u32).max(1);
+        let mut band = image::DynamicImage::new_rgba8(width, height);
+        for item in &layout.items {
+            let style = TextStyle {
+                size: item.style.size * scale,
+                letter_spacing: item.style.letter_spacing * scale,
+                ..item.style
+            };
+            let anchor = (item.anchor.0 * scale, (item.anchor.1 - layout.area_y) * scale);
+            text_render::render_text(&mut band, &item.fonts, &item.text, anchor, &style);
+        }
+        let band = band.to_rgba8();
+        let image = ColorImage::from_rgba_unmultiplied([width as usize, height as usize], &band);
+        // 文字纹理可能以导出分辨率生成后缩小显示，使用 mipmap 避免笔画闪烁
+        let handle = ctx.load_texture("text_preview", image, TextureOptions::LINEAR.with_mipmap_mode(Some(egui::TextureFilter::Linear)));
+        self.doc.text_preview_texture = Some((key, handle));
+    }
+
+    // 打开图片文件：在新标签页中后台加载（已打开则切换过去），取消之前未完成的加载
+    fn open_file(&mut self, file_path: &str) {
+        self.load_queue.clear();
+        self.request_load(file_path, LoadTarget::NewTab);
+    }
+
+    // 一次打开多张图片：逐张在新标签页中加载
+    fn open_files(&mut self, file_paths: Vec<String>) {
+        self.load_queue = file_paths.into();
+        self.pending_load = None;
+        self.load_next_in_queue();
+    }
+
+    // 胶片条切换照片：在当前标签页中换成另一张图片并沿用当前画框设置（图片已在其他标签页打开时切换过去）
+    fn open_in_current_tab(&mut self, file_path: &str) {
+        self.load_queue.clear();
+        let target = if self.doc.id == 0 { LoadTarget::NewTab } else { LoadTarget::Replace(self.doc.id) };
+        self.request_load(file_path, target);
+    }
+
+    // ========== 新增：后台加载 ==========
+    fn request_load(&mut self, file_path: &str, target: LoadTarget) {
+        if let Some(id) = self.find_doc(file_path) {
+            self.pending_load = None;
+            self.activate_doc(id);
+            self.focus_tab(id);
+            return;
+        }
+        if matches!(&self.pending_load, Some(pending) if pending.path.as_deref() == Some(file_path) && pending.target == target) {
+            return;
+        }
+        // 替换旧的加载时，旧的工作线程会收到取消通知
+        self.pending_load = Some(PendingLoad::start(
+            file_path.to_string(),
+            target,
+            self.default_rows.clone(),
+            PALETTE_SIZE,
+            self.max_texture_side,
+        ));
+    }
+
+    fn load_next_in_queue(&mut self) {
+        if let Some(next) = self.load_queue.pop_front() {
+            self.request_load(&next, LoadTarget::NewTab);
+        }
+    }
+
+    // 取消正在进行和排队中的加载
+    fn cancel_loading(&mut self) {
+        self.pending_load = None;
+        self.load_queue.clear();
+    }
+
+    // 正在浏览的图片：有加载中的图片时为该图片，否则为当前图片
+    fn browsing_file(&self) -> Option<String> {
+        match &self.pending_load {
+            Some(pending) => pending.path.clone(),
+            None => self.doc.selected_file.clone(),
+        }
+    }
+
+    // 每帧检查后台加载是否完成，完成后放入对应的标签页
+    fn poll_pending_load(&mut self, ctx: &egui::Context) {
+        let Some(result) = self.pending_load.as_ref().and_then(|pending| pending.poll()) else {
+            if self.pending_load.is_some() {
+                ctx.request_repaint_after(std::time::Duration::from_millis(50));
+            }
+            return;
+        };
+        let Some(pending) = self.pending_load.take() else {
+            return;
+        };
+        match result {
+            Ok(loaded) => self.finish_load(ctx, &pending, loaded),
+            Err(e) => {
+                eprintln!("Fail to load pictures: {}", e);
+                self.show_toast(format!("❌ 无法打开 {}: {}", pending.name, e), false);
+                // 文件已被移动或删除时，从最近打开列表中移除
+                if let Some(path) = &pending.path
+                    && !Path::new(path).exists()
+                {
+                    self.recent_files.retain(|f| f != path);
                 }
             }
-            return Err("Windows系统未找到可用字体".to_string());
-        } else if cfg!(linux) {
-            // Linux 系统字体
-            let font_paths = [
-                "/usr/share/fonts/truetype/freefont/FreeSans.ttf",
-                "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
-                "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf"
-            ];
-            for path in font_paths {
-                if let Ok(data) = std::fs::read(path) {
-                    eprintln!("加载Linux字体成功: {}", path);
-                    return Ok(data);
+        }
+        self.load_next_in_queue();
+    }
+
+    // 加载完成：新图片沿用当前（或被替换标签页）的画框设置，图片文件成功打开后加入最近打开列表
+    fn finish_load(&mut self, ctx: &egui::Context, pending: &PendingLoad, loaded: LoadedImage) {
+        let file_path = pending.path.as_deref();
+        match pending.target {
+            LoadTarget::Replace(id) if self.doc(id).is_some() => {
+                let mut doc = ImageDoc::blank(id, self.default_rows.clone(), self.doc(id));
+                doc.apply_loaded(ctx, file_path, &pending.name, loaded);
+                if id == self.doc.id {
+                    self.doc = doc;
+                    self.history_label = None;
+                } else {
+                    self.docs.insert(id, doc);
                 }
             }
-            return Err("Linux系统未找到可用字体".to_string());
-        } else if cfg!(macos) {
-            // macOS 系统字体
-            let font_paths = [
-                "/Library/Fonts/Arial.ttf",
-                "/System/Library/Fonts/PingFang.ttc",
-                "/System/Library/Fonts/Helvetica.ttf"
-            ];
-            for path in font_paths {
-                if let Ok(data) = std::fs::read(path) {
-                    eprintln!("加载macOS字体成功: {}", path);
-                    return Ok(data);
+            _ => {
+                let id = self.next_doc_id;
+                self.next_doc_id += 1;
+                let mut doc = ImageDoc::blank(id, self.default_rows.clone(), Some(&self.doc));
+                doc.apply_loaded(ctx, file_path, &pending.name, loaded);
+                self.commit_history();
+                let previous = std::mem::replace(&mut self.doc, doc);
+                if previous.id != 0 {
+                    self.docs.insert(previous.id, previous);
                 }
+                self.history_label = None;
+                self.dock_state.push_to_focused_leaf(id);
+                self.focus_tab(id);
             }
-            return Err("macOS系统未找到可用字体".to_string());
-        } else {
-            // 兜底：使用内置测试字体（需将字体文件放在项目根目录fonts文件夹）
-            if let Ok(data) = std::fs::read("fonts/DejaVuSans.ttf") {
-                eprintln!("加载内置字体成功");
-                return Ok(data);
+        }
+
+        if let Some(file_path) = file_path {
+            self.recent_files.retain(|f| f != file_path);
+            self.recent_files.insert(0, file_path.to_string());
+            self.recent_files.truncate(MAX_RECENT_FILES);
+        }
+    }
+
+    // 加载进度：转圈、文件名、当前阶段和取消按钮
+    fn show_loading_status(&mut self, ui: &mut egui::Ui) {
+        let Some(pending) = &self.pending_load else {
+            return;
+        };
+        let (stage, progress) = pending.progress();
+        let mut cancel = false;
+        ui.horizontal(|ui| {
+            ui.spinner();
+            ui.label(format!("正在加载 {}：{}", pending.name, stage));
+            ui.add(egui::ProgressBar::new(progress).desired_width(120.0));
+            if !self.load_queue.is_empty() {
+                ui.label(format!("（还有 {} 张等待加载）", self.load_queue.len()));
             }
-            return Err("未找到任何可用字体，请确保fonts/DejaVuSans.ttf存在".to_string());
+            cancel = ui.button("取消").clicked();
+        });
+        if cancel {
+            self.cancel_loading();
         }
     }
 
-    fn reset(&mut self) {
-        // 重置 EXIF 数据到初始状态
-        self.rows = self.initial_exif_data.clone();
-    }
-
-    fn read_exif(&mut self, path: &str) {
-        self.rows = self.default_rows.clone();
-        let extension = Path::new(path).extension().and_then(|ext| ext.to_str()).map(|s| s.to_lowercase());
-
-        match File::open(path) {
-            Ok(file) => {
-                match extension.as_deref() {
-                    Some("jpg") | Some("jpeg") => {
-                        let mut buf_reader = BufReader::new(&file);
-                        match Reader::new().read_from_container(&mut buf_reader) {
-                            Ok(exif) => {
-                                for row in &mut self.rows {
-                                    analyse_jpg(&exif, row);
-                                }
+    // ========== 新增：文件夹胶片条 ==========
+    // 打开文件夹：列出其中的图片并打开第一张
+    fn open_folder(&mut self, folder: &Path) {
+        self.filmstrip.open_folder(folder);
+        match self.filmstrip.entries.first().map(|entry| entry.path.clone()) {
+            Some(first) => self.open_file(&first),
+            None => self.show_toast("文件夹中没有可打开的图片".to_string(), false),
+        }
+    }
+
+    // 快捷键：←/→ 切换到文件夹中的上一张/下一张，Home/End 切换到第一张/最后一张
+    // （有控件获得焦点时方向键留给控件，例如滑块和输入框）
+    fn handle_filmstrip_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.memory(|m| m.focused().is_some()) || self.filmstrip.entries.is_empty() {
+            return;
+        }
+        let offset = ctx.input_mut(|i| {
+            if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowLeft) {
+                Some(-1)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowRight) {
+                Some(1)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Home) {
+                Some(isize::MIN)
+            } else if i.consume_key(egui::Modifiers::NONE, egui::Key::End) {
+                Some(isize::MAX)
+            } else {
+                None
+            }
+        });
+        let Some(offset) = offset else {
+            return;
+        };
+        let current = self.browsing_file().unwrap_or_default();
+        if let Some(target) = self.filmstrip.step(&current, offset).map(str::to_string)
+            && target != current
+        {
+            self.open_in_current_tab(&target);
+            self.filmstrip.scroll_to_current = true;
+        }
+    }
+
+    // ========== 新增：拖放打开文件 ==========
+    // 拖入一张图片时直接打开，拖入多张图片或文件夹时逐张在新标签页中加载；不支持的文件跳过并提示
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped: Vec<std::path::PathBuf> = ctx.input(|i| i.raw.dropped_files.iter().filter_map(|file| file.path.clone()).collect());
+        if dropped.is_empty() {
+            return;
+        }
+
+        // 文件夹展开为其中的图片（按文件名排序），其余按扩展名检查格式
+        let mut images = Vec::new();
+        let mut skipped = 0;
+        for path in dropped {
+            if path.is_dir() {
+                images.extend(filmstrip::list_images(&path));
+            } else if filmstrip::is_supported_image(&path) {
+                images.push(path);
+            } else {
+                eprintln!("不支持的文件格式: {}", path.display());
+                skipped += 1;
+            }
+        }
+
+        let paths: Vec<String> = images.iter().map(|path| path.display().to_string()).collect();
+        match paths.as_slice() {
+            [] => {
+                self.show_toast("没有可打开的图片（支持 PNG、JPG、JPEG）".to_string(), false);
+                return;
+            }
+            [path] => self.open_file(path),
+            _ => self.open_files(paths),
+        }
+        if skipped > 0 {
+            self.show_toast(format!("已跳过 {} 个不支持的文件", skipped), false);
+        }
+    }
+
+    // 拖动文件经过窗口时覆盖一层提示：显示将要打开的数量，全部不是图片时提示格式不支持。
+    // 部分平台拖动过程中拿不到路径，此时只显示通用提示
+    fn show_drop_overlay(&self, ctx: &egui::Context) {
+        let hovered: Vec<Option<std::path::PathBuf>> = ctx.input(|i| i.raw.hovered_files.iter().map(|file| file.path.clone()).collect());
+        if hovered.is_empty() {
+            return;
+        }
+        let known: Vec<&std::path::PathBuf> = hovered.iter().flatten().collect();
+        let accepted = known.iter().filter(|path| path.is_dir() || filmstrip::is_supported_image(path)).count();
+        let (message, color) = if known.len() < hovered.len() {
+            ("松开鼠标打开图片".to_string(), egui::Color32::from_rgb(120, 200, 255))
+        } else if accepted == 0 {
+            ("不支持的文件格式（支持 PNG、JPG、JPEG 和文件夹）".to_string(), egui::Color32::from_rgb(255, 110, 110))
+        } else if accepted < known.len() {
+            (format!("松开鼠标打开 {} 项（跳过 {} 个不支持的文件）", accepted, known.len() - accepted), egui::Color32::from_rgb(255, 200, 90))
+        } else {
+            (format!("松开鼠标打开 {} 项", accepted), egui::Color32::from_rgb(120, 200, 255))
+        };
+
+        let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Foreground, egui::Id::new("drop_overlay")));
+        let screen = ctx.content_rect();
+        painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+        painter.rect_stroke(screen.shrink(12.0), 12.0, egui::Stroke::new(3.0, color), egui::StrokeKind::Inside);
+        painter.text(screen.center(), egui::Align2::CENTER_CENTER, message, egui::FontId::proportional(24.0), color);
+    }
+
+    // 底部胶片条：当前文件夹中所有图片的缩略图，点击切换当前标签页的图片
+    fn show_filmstrip(&mut self, ctx: &egui::Context) {
+        // 胶片条跟随当前图片所在的文件夹
+        if let Some(folder) = self.doc.selected_file.as_deref().and_then(|f| Path::new(f).parent()).map(Path::to_path_buf) {
+            self.filmstrip.open_folder(&folder);
+        }
+        if self.filmstrip.entries.is_empty() {
+            return;
+        }
+        let loading = self.filmstrip.poll(ctx);
+        if loading {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let current = self.browsing_file();
+        let current_index = current.as_deref().and_then(|file| self.filmstrip.position(file));
+        let scroll_to_current = std::mem::take(&mut self.filmstrip.scroll_to_current);
+        let mut clicked = None;
+        egui::TopBottomPanel::bottom("filmstrip").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let count = self.filmstrip.entries.len();
+                match current_index {
+                    Some(index) => ui.label(format!("{} / {}", index + 1, count)),
+                    None => ui.label(format!("{} 张图片", count)),
+                };
+                if loading {
+                    ui.spinner();
+                    ui.label("正在生成缩略图…");
+                }
+                ui.weak("←/→ 切换照片");
+            });
+
+            // 缩略图以固定大小的方框显示，图片按比例缩放居中
+            let box_size = filmstrip::THUMBNAIL_SIZE as f32 * 0.6;
+            ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for (index, entry) in self.filmstrip.entries.iter().enumerate() {
+                        let (rect, response) = ui.allocate_exact_size(egui::vec2(box_size, box_size), egui::Sense::click());
+                        let selected = current_index == Some(index);
+                        if selected {
+                            ui.painter().rect_filled(rect, 4.0, ui.visuals().selection.bg_fill);
+                        } else if response.hovered() {
+                            ui.painter().rect_filled(rect, 4.0, ui.visuals().widgets.hovered.bg_fill);
+                        }
+                        match &entry.texture {
+                            Some(texture) => {
+                                let size = texture.size_vec2();
+                                let scale = (box_size - 8.0) / size.max_elem().max(1.0);
+                                ui.painter().image(
+                                    texture.id(),
+                                    egui::Rect::from_center_size(rect.center(), size * scale),
+                                    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                                    egui::Color32::WHITE,
+                                );
                             }
-                            Err(e) => {
-                                eprintln!("无法读取JPEG EXIF数据: {}", e);
-                                if let Some(first_row) = self.rows.first_mut() {
-                                    first_row.tag_value = format!("无法读取EXIF数据: {}", e);
-                                }
+                            None => {
+                                let text = if entry.failed { "无法预览" } else { "…" };
+                                ui.painter().text(
+                                    rect.center(),
+                                    egui::Align2::CENTER_CENTER,
+                                    text,
+                                    egui::FontId::proportional(12.0),
+                                    ui.visuals().weak_text_color(),
+                                );
                             }
                         }
-                    }
-                    Some("png") => {
-                        // 对于 PNG，只解析一次文件，然后使用数据更新所有行
-                        if let Ok(mut file) = File::open(path) {
-                            let mut buf_reader = BufReader::new(&mut file);
-                            match get_png_exif(&mut buf_reader) {
-                                Ok((exif_data, dimensions, xmp_exif_data)) => {
-                                    // 将 Vec<u8> 包装为 Option<Vec<u8>>
-                                    let exif_data_opt = if exif_data.is_empty() {
-                                        None
-                                    } else {
-                                        Some(exif_data)
-                                    };
-                                    
-                                    // 使用获取的数据更新所有行
-                                    for row in &mut self.rows {
-                                        analyse_png(row, &exif_data_opt, &dimensions, &xmp_exif_data);
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!("PNG EXIF extraction error: {}", e);
-                                    for row in &mut self.rows {
-                                        if row.tag_value.is_empty() {
-                                            row.tag_value = "无EXIF数据".to_string();
-                                        }
-                                    }
-                                }
-                            }
+                        if selected && scroll_to_current {
+                            response.scroll_to_me(Some(egui::Align::Center));
                         }
-                    }
-                    _ => {
-                        // 对于其他格式，显示不支持的信息
-                        if let Some(first_row) = self.rows.first_mut() {
-                            first_row.tag_value = "不支持该格式的EXIF读取".to_string();
+                        if response.on_hover_text(&entry.name).clicked() {
+                            clicked = Some(entry.path.clone());
                         }
                     }
+                });
+            });
+        });
+
+        if let Some(path) = clicked {
+            self.open_in_current_tab(&path);
+        }
+    }
+
+    // ========== 新增：多图片标签页 ==========
+    fn doc(&self, id: u64) -> Option<&ImageDoc> {
+        if self.doc.id == id {
+            Some(&self.doc)
+        } else {
+            self.docs.get(&id)
+        }
+    }
+
+    // 查找已打开该文件的标签页
+    fn find_doc(&self, file_path: &str) -> Option<u64> {
+        std::iter::once(&self.doc)
+            .chain(self.docs.values())
+            .find(|doc| doc.selected_file.as_deref() == Some(file_path))
+            .map(|doc| doc.id)
+    }
+
+    // 切换左侧面板编辑的图片，切换前保存当前图片未记录的修改
+    fn activate_doc(&mut self, id: u64) {
+        if id == self.doc.id {
+            return;
+        }
+        let Some(mut doc) = self.docs.remove(&id) else {
+            return;
+        };
+        self.commit_history();
+        std::mem::swap(&mut self.doc, &mut doc);
+        if doc.id != 0 {
+            self.docs.insert(doc.id, doc);
+        }
+        self.history_label = None;
+    }
+
+    // 让标签页成为所在区域的当前页并获得焦点
+    fn focus_tab(&mut self, id: u64) {
+        if let Some((surface, node, tab)) = self.dock_state.find_tab(&id) {
+            self.dock_state.set_active_tab((surface, node, tab));
+            self.dock_state.set_focused_node_and_surface((surface, node));
+        }
+    }
+
+    // 关闭标签页；关闭的是当前图片时换成空白文档（保留画框设置），下一帧再激活其他标签页
+    fn close_doc(&mut self, id: u64) {
+        if id == self.doc.id {
+            self.doc = ImageDoc::blank(0, self.default_rows.clone(), Some(&self.doc));
+            self.history_label = None;
+        } else {
+            self.docs.remove(&id);
+        }
+    }
+
+    // 绘制标签页后，让左侧面板编辑获得焦点的标签页
+    fn sync_active_tab(&mut self) {
+        let focused = self.dock_state.find_active_focused().map(|(_, id)| *id);
+        match focused {
+            Some(id) => self.activate_doc(id),
+            None if self.doc.id == 0 => {
+                let first = self.dock_state.iter_all_tabs().next().map(|(_, id)| *id);
+                if let Some(id) = first {
+                    self.activate_doc(id);
+                    self.focus_tab(id);
                 }
             }
-            Err(e) => {
-                eprintln!("无法打开文件: {}", e);
-                if let Some(first_row) = self.rows.first_mut() {
-                    first_row.tag_value = format!("无法打开文件: {}", e);
-                }
-            }
+            None => {}
         }
     }
 
-}
+    // 把当前图片的画框设置应用到所有标签页（各自记录一条历史，可单独撤销）
+    fn sync_frame_to_all_docs(&mut self) {
+        for doc in self.docs.values_mut() {
+            doc.frame = self.doc.frame.clone();
+            doc.bg_texture = self.doc.bg_texture.clone();
+            doc.bg_texture_color = self.doc.bg_texture_color;
+            let state = doc.edit_state();
+            doc.history.record("同步画框设置", state);
+        }
+    }
+
+    // ========== 新增：预览缩放和平移 ==========
+    // 工具栏：适应/填充/100% 切换、当前缩放比例和放大镜开关
+    fn show_zoom_toolbar(&mut self, ui: &mut egui::Ui) {
+        let ppp = ui.ctx().pixels_per_point();
+        ui.horizontal(|ui| {
+            let view = &mut self.doc.view;
+            for (mode, label) in [(ZoomMode::Fit, "适应"), (ZoomMode::Fill, "填充"), (ZoomMode::Actual, "100%")] {
+                if ui.selectable_label(view.mode == mode, label).clicked() {
+                    view.mode = mode;
+                    view.pan = egui::Vec2::ZERO;
+                }
+            }
+            // 显示比例以物理像素计：100% 为原图一个像素对应屏幕一个像素
+            ui.label(format!("{:.0}%", view.scale * ppp * 100.0));
+            // 大尺寸照片的预览纹理是缩小后的，超过该比例放大时看到的只是放大的预览纹理
+            if let (Some(texture), Some((source_width, _))) = (&self.doc.texture, self.doc.source_size()) {
+                let preview_ratio = texture.size()[0] as f32 / source_width as f32;
+                if preview_ratio < 1.0 {
+                    ui.weak(format!("（预览分辨率 {:.0}%）", preview_ratio * 100.0))
+                        .on_hover_text("预览纹理已缩小，更大的比例只是放大预览纹理；查看原图像素请使用放大镜或精确预览");
+                }
+            }
+            ui.separator();
+            ui.checkbox(&mut self.show_loupe, "放大镜");
+            ui.checkbox(&mut self.accurate_preview, "精确预览")
+                .on_hover_text("在后台按预览分辨率运行完整的导出流程，显示与导出文件一致的画面");
+            ui.weak("滚轮缩放 · 拖动平移 · 双击切换适应/100%");
+        });
+    }
+
+    // 可缩放、拖动的画框预览
+    fn show_canvas(&mut self, ui: &mut egui::Ui, texture: &TextureHandle) {
+        let ctx = ui.ctx().clone();
+        let ppp = ctx.pixels_per_point();
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+
+        // 边框以原图像素计算，与导出保持一致的比例
+        let image_size = self.doc.image_size;
+        let borders = self.frame_borders(image_size.x as u32, image_size.y as u32);
+        let bg_size = egui::vec2(
+            image_size.x + (borders.left + borders.right) as f32,
+            image_size.y + (borders.up + borders.down) as f32,
+        );
+        if bg_size.x <= 0.0 || bg_size.y <= 0.0 || rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+
+        // 当前模式下每个原图像素对应的点数
+        let fit_x = rect.width() / bg_size.x;
+        let fit_y = rect.height() / bg_size.y;
+        let mut view = self.doc.view;
+        let mut scale = match view.mode {
+            ZoomMode::Fit => fit_x.min(fit_y).min(1.0), // 完整显示，不放大
+            ZoomMode::Fill => fit_x.max(fit_y),
+            ZoomMode::Actual => 1.0 / ppp,
+            ZoomMode::Custom => view.scale,
+        };
+
+        // 滚轮（或触控板捏合、Ctrl+滚轮）以光标为中心缩放：光标下的画面位置保持不动
+        if response.hovered() {
+            let factor = ui.input(|i| i.zoom_delta() * (i.smooth_scroll_delta.y * 0.0015).exp());
+            if factor != 1.0 {
+                let new_scale = (scale * factor).clamp(MIN_PREVIEW_ZOOM / ppp, MAX_PREVIEW_ZOOM / ppp);
+                if let Some(pointer) = response.hover_pos() {
+                    let offset = pointer - (rect.center() + view.pan);
+                    view.pan += offset - offset * (new_scale / scale);
+                }
+                view.mode = ZoomMode::Custom;
+                scale = new_scale;
+            }
+        }
+        // 双击在适应和 100% 之间切换，放大时以双击位置为中心
+        if response.double_clicked() {
+            if view.mode == ZoomMode::Fit {
+                let new_scale = 1.0 / ppp;
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    view.pan = (rect.center() - pointer) * (new_scale / scale);
+                }
+                view.mode = ZoomMode::Actual;
+                scale = new_scale;
+            } else {
+                view = PreviewView::default();
+                scale = fit_x.min(fit_y).min(1.0);
+            }
+        }
+        // 拖动平移
+        if response.dragged() {
+            view.pan += response.drag_delta();
+            view.mode = ZoomMode::Custom;
+        }
+        // 至少保留一部分画面在预览区域内
+        let limit = ((bg_size * scale + rect.size()) * 0.5 - egui::Vec2::splat(32.0)).max(egui::Vec2::ZERO);
+        view.pan = view.pan.clamp(-limit, limit);
+        view.scale = scale;
+        self.doc.view = view;
+        response.clone().on_hover_cursor(if response.dragged() {
+            egui::CursorIcon::Grabbing
+        } else {
+            egui::CursorIcon::Grab
+        });
+
+        // 文字纹理按屏幕实际像素生成，避免缩放后模糊；比例按半个八度取整，缩放过程中不必每帧重新光栅化；
+        // 最高为导出分辨率（放大镜打开时直接使用导出分辨率）
+        let layout = self.frame_text_layout(image_size.x as u32, image_size.y as u32);
+        let max_text_scale = (self.max_texture_side as f32 / bg_size.x).min(1.0);
+        let text_scale = if self.show_loupe { 1.0 } else { quantize_preview_scale(scale * ppp) };
+        self.update_text_preview_texture(&ctx, &layout, bg_size.x, text_scale.min(max_text_scale));
+
+        let bg_rect = egui::Rect::from_center_size(rect.center() + view.pan, bg_size * scale);
+        let painter = ui.painter_at(rect);
+        let (accurate_texture, rendering) = if self.accurate_preview {
+            self.update_accurate_preview(&ctx, scale * ppp)
+        } else {
+            (None, false)
+        };
+        match &accurate_texture {
+            Some(accurate) => {
+                let full_uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+                painter.image(accurate.id(), bg_rect, full_uv, egui::Color32::WHITE);
+            }
+            // 第一次渲染完成前先显示快速预览
+            None => self.paint_frame_scene(&painter, bg_rect.min, scale, &borders, PreviewPhoto::Source(texture), &layout),
+        }
+        if rendering {
+            let status_rect = egui::Rect::from_min_size(rect.min + egui::vec2(8.0, 8.0), egui::vec2(200.0, 20.0));
+            ui.put(status_rect, |ui: &mut egui::Ui| {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(RichText::new("正在渲染精确预览…").small());
+                })
+                .response
+            });
+        }
+
+        // 放大镜：在光标旁以原图分辨率显示光标附近的画面
+        if let Some(pointer) = response.hover_pos().filter(|_| self.show_loupe && !response.dragged()) {
+            let loupe_scale = (2.0 / ppp).max(scale * 2.0);
+            let canvas_pos = (pointer - bg_rect.min) / scale; // 光标处的画框像素坐标
+
+            // 放大镜默认在光标右上方，靠近边缘时换到另一侧
+            let gap = LOUPE_SIZE * 0.5 + 16.0;
+            let mut center = pointer + egui::vec2(gap, -gap);
+            if center.x + LOUPE_SIZE * 0.5 > rect.right() {
+                center.x = pointer.x - gap;
+            }
+            if center.y - LOUPE_SIZE * 0.5 < rect.top() {
+                center.y = pointer.y + gap;
+            }
+            let loupe_rect = egui::Rect::from_center_size(center, egui::Vec2::splat(LOUPE_SIZE));
+
+            let loupe_painter = painter.with_clip_rect(loupe_rect.intersect(rect));
+            loupe_painter.rect_filled(loupe_rect, 0.0, ui.visuals().extreme_bg_color);
+            let origin = loupe_rect.center() - canvas_pos * loupe_scale;
+            let photo_pos = canvas_pos - egui::vec2(borders.left as f32, borders.up as f32);
+            let half = LOUPE_SIZE * 0.5 / loupe_scale;
+            let loupe_photo = self.loupe_texture(&ctx, photo_pos, half);
+            // 光标附近没有照片（位于边框上）时只绘制背景和文字
+            let photo = match &loupe_photo {
+                Some((crop_texture, part)) => PreviewPhoto::Part(crop_texture, *part),
+                None => PreviewPhoto::None,
+            };
+            self.paint_frame_scene(&loupe_painter, origin, loupe_scale, &borders, photo, &layout);
+            painter.rect_stroke(loupe_rect, 0.0, egui::Stroke::new(2.0, egui::Color32::WHITE), egui::StrokeKind::Outside);
+            painter.text(
+                loupe_rect.left_bottom() + egui::vec2(6.0, -6.0),
+                egui::Align2::LEFT_BOTTOM,
+                format!("{:.0}%", loupe_scale * ppp * 100.0),
+                egui::FontId::proportional(12.0),
+                egui::Color32::WHITE,
+            );
+        }
+    }
+
+    // 按给定比例绘制画框（背景、投影、照片、色板条和文字），origin 为画框左上角的屏幕坐标。
+    // 主预览绘制整张照片的原图纹理，放大镜只使用调整后照片的一小块
+    fn paint_frame_scene(
+        &self,
+        painter: &egui::Painter,
+        origin: egui::Pos2,
+        scale: f32,
+        borders: &Borders,
+        photo: PreviewPhoto,
+        layout: &FrameTextLayout,
+    ) {
+        let image_size = self.doc.image_size;
+        let full_uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+        let bg_size = egui::vec2(
+            image_size.x + (borders.left + borders.right) as f32,
+            image_size.y + (borders.up + borders.down) as f32,
+        );
+        let bg_rect = egui::Rect::from_min_size(origin, bg_size * scale);
+
+        // 绘制背景框
+        if let Some((_, bg_texture)) = &self.doc.bg_preview_texture {
+            painter.image(bg_texture.id(), bg_rect, full_uv, egui::Color32::WHITE);
+        } else {
+            painter.rect_filled(bg_rect, 0.0, self.effective_bg_color());
+        }
+
+        // 图片左上角 = 背景左上角 + 缩放后的边框
+        let image_rect = egui::Rect::from_min_size(
+            origin + egui::vec2(borders.left as f32, borders.up as f32) * scale,
+            image_size * scale,
+        );
+
+        // 圆角和投影按原图像素计算后同比缩放
+        let (corner_radius, shadow_dx, shadow_dy, shadow_blur) =
+            self.photo_effect_pixels(image_size.x as u32, image_size.y as u32);
+        // egui 的圆角半径最大为 255 点，超过时照片改用网格绘制（投影边缘经过模糊，仍按 255 点近似）
+        let screen_radius = corner_radius * scale;
+        let preview_radius = egui::CornerRadius::same(screen_radius.round().min(255.0) as u8);
+        let mesh_radius = screen_radius > 255.0;
+
+        // 绘制投影（egui 的模糊宽度为整个渐变带宽度，约为模糊半径的2倍）
+        if self.doc.frame.shadow_enabled && self.doc.frame.shadow_opacity > 0.0 {
+            let shadow_rect = image_rect.translate(egui::vec2(shadow_dx, shadow_dy) * scale);
+            painter.add(
+                egui::epaint::RectShape::filled(
+                    shadow_rect,
+                    preview_radius,
+                    egui::Color32::from_black_alpha((self.doc.frame.shadow_opacity * 255.0) as u8),
+                )
+                .with_blur_width(shadow_blur * 2.0 * scale),
+            );
+        }
+
+        // 绘制图片（只有整张照片时才能套用圆角）
+        match photo {
+            PreviewPhoto::Source(texture) if self.doc.geometry.is_identity() && !mesh_radius => {
+                painter.add(
+                    egui::epaint::RectShape::filled(image_rect, preview_radius, egui::Color32::WHITE)
+                        .with_texture(texture.id(), full_uv),
+                );
+            }
+            // 有几何调整（或圆角超出 egui 的上限）时按调整参数换算每个顶点的纹理坐标
+            PreviewPhoto::Source(texture) => {
+                if let Some(source_size) = self.doc.source_size() {
+                    painter.add(geometry_mesh(texture.id(), source_size, &self.doc.geometry, image_rect, screen_radius));
+                }
+            }
+            PreviewPhoto::Part(texture, part) if part.is_positive() => {
+                let part_rect = egui::Rect::from_min_max(
+                    image_rect.min + part.min.to_vec2() * scale,
+                    image_rect.min + part.max.to_vec2() * scale,
+                );
+                let whole = part == egui::Rect::from_min_size(egui::Pos2::ZERO, image_size);
+                if whole && mesh_radius {
+                    let [width, height] = texture.size();
+                    let size = (width as u32, height as u32);
+                    painter.add(geometry_mesh(texture.id(), size, &Geometry::default(), part_rect, screen_radius));
+                } else {
+                    let radius = if whole { preview_radius } else { egui::CornerRadius::ZERO };
+                    painter.add(
+                        egui::epaint::RectShape::filled(part_rect, radius, egui::Color32::WHITE)
+                            .with_texture(texture.id(), full_uv),
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        // ========== 新增：绘制色板条、迷你直方图和画框文字（与导出共用排版） ==========
+        for (rect, color) in &layout.swatches {
+            let rect = egui::Rect::from_min_size(origin + rect.min.to_vec2() * scale, rect.size() * scale);
+            painter.rect_filled(rect, 0.0, *color);
+        }
+        if let Some((rect, heights, color)) = &layout.histogram {
+            let rect = egui::Rect::from_min_size(origin + rect.min.to_vec2() * scale, rect.size() * scale);
+            let bar_width = rect.width() / heights.len() as f32;
+            let color = color.gamma_multiply(FRAME_HISTOGRAM_OPACITY);
+            for (i, height) in heights.iter().enumerate() {
+                let left = rect.left() + i as f32 * bar_width;
+                let bar = egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - height * rect.height()),
+                    egui::pos2(left + bar_width, rect.bottom()),
+                );
+                painter.rect_filled(bar, 0.0, color);
+            }
+        }
+        if let Some((_, text_texture)) = &self.doc.text_preview_texture {
+            let text_rect = egui::Rect::from_min_size(
+                egui::pos2(origin.x, origin.y + layout.area_y * scale),
+                egui::vec2(bg_rect.width(), layout.area_height * scale),
+            );
+            painter.image(text_texture.id(), text_rect, full_uv, egui::Color32::WHITE);
+        }
+
+        // 替代方案（简化模糊占位，保留开关逻辑）
+        // if self.doc.frame.enable_blur_bg {
+        //     // egui 0.33 需通过 glow 上下文直接操作，此处先保留开关，后续单独实现
+        //     painter.rect_filled(
+        //         bg_rect,
+        //         egui::Rounding::ZERO,
+        //         egui::Color32::from_rgba_premultiplied(
+        //             self.doc.frame.bg_color.r(),
+        //             self.doc.frame.bg_color.g(),
+        //             self.doc.frame.bg_color.b(),
+        //             (self.doc.frame.bg_color.a() as f32 * 0.8) as u8 // 模拟模糊透明度
+        //         )
+        //     );
+        // }
+    }
+
+    // 放大镜使用的原图局部纹理（已按几何调整处理）：以 64 像素为网格取区域，光标在网格内移动时复用，
+    // 只对该区域按几何调整取值，不处理整张原图；返回纹理及其覆盖的照片像素范围，光标附近没有照片时返回 None
+    fn loupe_texture(&mut self, ctx: &egui::Context, center: egui::Vec2, half: f32) -> Option<(TextureHandle, egui::Rect)> {
+        const GRID: f32 = 64.0;
+        let image = self.doc.original_image.clone()?;
+        let (width, height) = self.doc.geometry.output_size(image.width(), image.height());
+        let (width, height) = (width as f32, height as f32);
+        let x0 = (((center.x - half) / GRID).floor() * GRID).clamp(0.0, width);
+        let y0 = (((center.y - half) / GRID).floor() * GRID).clamp(0.0, height);
+        let x1 = (((center.x + half) / GRID).ceil() * GRID).clamp(0.0, width);
+        let y1 = (((center.y + half) / GRID).ceil() * GRID).clamp(0.0, height);
+        if x1 <= x0 || y1 <= y0 {
+            return None;
+        }
+        let key = [x0 as u32, y0 as u32, x1 as u32, y1 as u32];
+        let part = egui::Rect::from_min_max(egui::pos2(x0, y0), egui::pos2(x1, y1));
+        if let Some((geometry, cached, handle)) = &self.doc.loupe_texture
+            && *geometry == self.doc.geometry
+            && *cached == key
+        {
+            return Some((handle.clone(), part));
+        }
+        let crop = self.doc.geometry.apply_region(&image, (key[0], key[1], key[2] - key[0], key[3] - key[1]));
+        let crop = match &self.doc.icc_profile {
+            Some(icc) => icc.to_srgb(&crop).to_rgba8(),
+            None => crop.to_rgba8(),
+        };
+        let color_image = ColorImage::from_rgba_unmultiplied([crop.width() as usize, crop.height() as usize], &crop);
+        // 像素级检查使用最近邻采样，放大后每个像素清晰可见
+        let handle = ctx.load_texture("loupe", color_image, TextureOptions::NEAREST);
+        self.doc.loupe_texture = Some((self.doc.geometry, key, handle.clone()));
+        Some((handle, part))
+    }
+
+    // ========== 新增：裁剪 ==========
+    // 裁剪比例换算为裁剪框比例坐标下的宽 / 高（以拉直后的图片为准），自由裁剪时为 None
+    fn crop_aspect(&self) -> Option<f32> {
+        let (width, height) = self.doc.source_size()?;
+        let (width, height) = self.doc.geometry.straightened_size(width, height);
+        let aspect = self.crop_ratio.aspect(width, height)?;
+        let aspect = if self.crop_ratio_portrait { 1.0 / aspect } else { aspect };
+        Some(aspect * height as f32 / width as f32)
+    }
+
+    // 切换裁剪比例后，以当前裁剪框的中心为准换成该比例下最大的裁剪框
+    fn apply_crop_ratio(&mut self) {
+        if let Some(aspect) = self.crop_aspect() {
+            let crop = self.doc.geometry.crop.unwrap_or(CropRect::FULL);
+            self.doc.geometry.crop = Some(crop.with_aspect(aspect, 0, 0));
+        }
+    }
+
+    // 裁剪工具栏：裁剪比例、重置和完成
+    fn show_crop_toolbar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("裁剪比例:");
+            let before = (self.crop_ratio, self.crop_ratio_portrait);
+            ComboBox::from_id_salt("crop_ratio")
+                .selected_text(self.crop_ratio.label())
+                .width(90.0)
+                .show_ui(ui, |ui| {
+                    for ratio in CropRatio::ALL {
+                        ui.selectable_value(&mut self.crop_ratio, ratio, ratio.label());
+                    }
+                });
+            ui.add_enabled(self.crop_ratio != CropRatio::Free, egui::Checkbox::new(&mut self.crop_ratio_portrait, "竖向"));
+            if (self.crop_ratio, self.crop_ratio_portrait) != before {
+                self.apply_crop_ratio();
+            }
+            if ui.button("重置裁剪").clicked() {
+                self.doc.geometry.crop = None;
+            }
+            if ui.button("完成").clicked() {
+                self.crop_mode = false;
+            }
+            ui.weak("拖动边角调整大小 · 拖动框内移动");
+        });
+    }
+
+    // 裁剪编辑：显示未裁剪的完整画面，拖动裁剪框的边、角或内部调整裁剪范围
+    fn show_crop_editor(&mut self, ui: &mut egui::Ui, texture: &TextureHandle) {
+        let Some(source_size) = self.doc.source_size() else {
+            return;
+        };
+        let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::drag());
+        let uncropped = self.doc.geometry.uncropped();
+        let (width, height) = uncropped.output_size(source_size.0, source_size.1);
+        let margin = CROP_HANDLE_SIZE * 2.0;
+        let scale = ((rect.width() - margin) / width as f32).min((rect.height() - margin) / height as f32);
+        if scale <= 0.0 {
+            return;
+        }
+        let photo_rect = egui::Rect::from_center_size(rect.center(), egui::vec2(width as f32, height as f32) * scale);
+        let painter = ui.painter_at(rect);
+        painter.add(geometry_mesh(texture.id(), source_size, &uncropped, photo_rect, 0.0));
+
+        // 拖动开始时记录拖动方式和当时的裁剪框，之后按总位移计算，拖到边界外再拖回时不会错位
+        let crop = self.doc.geometry.crop.unwrap_or(CropRect::FULL);
+        let to_screen = |crop: CropRect| {
+            egui::Rect::from_min_max(
+                photo_rect.lerp_inside(egui::vec2(crop.x0, crop.y0)),
+                photo_rect.lerp_inside(egui::vec2(crop.x1, crop.y1)),
+            )
+        };
+        let drag_id = response.id.with("crop_drag");
+        if response.drag_started()
+            && let Some(handle) = response.interact_pointer_pos().and_then(|pos| crop_handle_at(to_screen(crop), pos))
+        {
+            ui.data_mut(|data| data.insert_temp(drag_id, (handle, crop)));
+        }
+        let dragging = ui.data(|data| data.get_temp::<(CropHandle, CropRect)>(drag_id));
+        if let Some((handle, start)) = dragging {
+            let origin = ui.input(|i| i.pointer.press_origin());
+            if let (Some(origin), Some(pointer)) = (origin, response.interact_pointer_pos()) {
+                let delta = (pointer - origin) / photo_rect.size();
+                self.doc.geometry.crop = Some(start.drag(handle, (delta.x, delta.y), self.crop_aspect()));
+            }
+            if response.drag_stopped() {
+                ui.data_mut(|data| data.remove::<(CropHandle, CropRect)>(drag_id));
+            }
+        }
+        let handle = dragging
+            .map(|(handle, _)| handle)
+            .or_else(|| response.hover_pos().and_then(|pos| crop_handle_at(to_screen(crop), pos)));
+        if let Some(handle) = handle {
+            ui.ctx().set_cursor_icon(match handle {
+                CropHandle::Move => egui::CursorIcon::Move,
+                CropHandle::Resize { horizontal, vertical } => match horizontal * vertical {
+                    1 => egui::CursorIcon::ResizeNwSe,
+                    -1 => egui::CursorIcon::ResizeNeSw,
+                    _ if horizontal != 0 => egui::CursorIcon::ResizeHorizontal,
+                    _ => egui::CursorIcon::ResizeVertical,
+                },
+            });
+        }
+
+        // 裁剪框外的部分调暗，框内绘制三分线和边角
+        let crop = self.doc.geometry.crop.unwrap_or(CropRect::FULL);
+        let crop_rect = to_screen(crop);
+        let shade = egui::Color32::from_black_alpha(150);
+        for outside in [
+            egui::Rect::from_min_max(photo_rect.min, egui::pos2(photo_rect.right(), crop_rect.top())),
+            egui::Rect::from_min_max(egui::pos2(photo_rect.left(), crop_rect.bottom()), photo_rect.max),
+            egui::Rect::from_min_max(egui::pos2(photo_rect.left(), crop_rect.top()), crop_rect.left_bottom()),
+            egui::Rect::from_min_max(crop_rect.right_top(), egui::pos2(photo_rect.right(), crop_rect.bottom())),
+        ] {
+            painter.rect_filled(outside, 0.0, shade);
+        }
+        let guide = egui::Stroke::new(1.0, egui::Color32::from_white_alpha(100));
+        for i in 1..3 {
+            let t = i as f32 / 3.0;
+            let x = egui::lerp(crop_rect.x_range(), t);
+            let y = egui::lerp(crop_rect.y_range(), t);
+            painter.line_segment([egui::pos2(x, crop_rect.top()), egui::pos2(x, crop_rect.bottom())], guide);
+            painter.line_segment([egui::pos2(crop_rect.left(), y), egui::pos2(crop_rect.right(), y)], guide);
+        }
+        painter.rect_stroke(crop_rect, 0.0, egui::Stroke::new(1.5, egui::Color32::WHITE), egui::StrokeKind::Middle);
+        for corner in [crop_rect.left_top(), crop_rect.right_top(), crop_rect.left_bottom(), crop_rect.right_bottom()] {
+            painter.rect_filled(egui::Rect::from_center_size(corner, egui::Vec2::splat(8.0)), 0.0, egui::Color32::WHITE);
+        }
+
+        // 裁剪后的像素尺寸
+        let (crop_width, crop_height) = self.doc.geometry.output_size(source_size.0, source_size.1);
+        painter.text(
+            crop_rect.left_top() + egui::vec2(6.0, 6.0),
+            egui::Align2::LEFT_TOP,
+            format!("{} x {}", crop_width, crop_height),
+            egui::FontId::proportional(12.0),
+            egui::Color32::WHITE,
+        );
+    }
+
+    // ========== 新增：直方图面板 ==========
+    // 显示 RGB 或亮度直方图，两端的三角形指示暗部/高光溢出（超过阈值时点亮）
+    fn show_histogram_panel(&mut self, ui: &mut egui::Ui) {
+        // 溢出像素超过该比例时点亮指示
+        const CLIPPING_THRESHOLD: f32 = 0.001;
+        let Some(stats) = &self.doc.histogram else {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("正在统计直方图…");
+            });
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.histogram_luma, false, "RGB");
+            ui.selectable_value(&mut self.histogram_luma, true, "亮度");
+        });
+
+        let size = egui::vec2(ui.available_width().min(320.0), 120.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 4.0, egui::Color32::from_gray(24));
+
+        let columns = 128;
+        let bar_width = rect.width() / columns as f32;
+        let channels: &[(histogram::Channel, egui::Color32)] = if self.histogram_luma {
+            &[(histogram::Channel::Luma, egui::Color32::from_gray(200))]
+        } else {
+            &[
+                (histogram::Channel::Red, egui::Color32::from_rgba_unmultiplied(255, 60, 60, 110)),
+                (histogram::Channel::Green, egui::Color32::from_rgba_unmultiplied(60, 255, 60, 110)),
+                (histogram::Channel::Blue, egui::Color32::from_rgba_unmultiplied(60, 120, 255, 110)),
+            ]
+        };
+        for (channel, color) in channels {
+            for (i, height) in stats.heights(*channel, columns).into_iter().enumerate() {
+                let left = rect.left() + i as f32 * bar_width;
+                let bar = egui::Rect::from_min_max(
+                    egui::pos2(left, rect.bottom() - height * (rect.height() - 4.0)),
+                    egui::pos2(left + bar_width, rect.bottom()),
+                );
+                painter.rect_filled(bar, 0.0, *color);
+            }
+        }
+
+        // 左上角为暗部溢出、右上角为高光溢出指示
+        let (shadows, highlights) = (stats.shadow_clipping(), stats.highlight_clipping());
+        let indicator = |corner: egui::Pos2, direction: f32, clipping: f32, lit: egui::Color32| {
+            let color = if clipping > CLIPPING_THRESHOLD { lit } else { egui::Color32::from_gray(70) };
+            let points = vec![
+                corner + egui::vec2(4.0 * direction, 4.0),
+                corner + egui::vec2(16.0 * direction, 4.0),
+                corner + egui::vec2(4.0 * direction, 16.0),
+            ];
+            painter.add(egui::Shape::convex_polygon(points, color, egui::Stroke::NONE));
+        };
+        indicator(rect.left_top(), 1.0, shadows, egui::Color32::from_rgb(80, 140, 255));
+        indicator(rect.right_top(), -1.0, highlights, egui::Color32::from_rgb(255, 80, 80));
+
+        ui.horizontal(|ui| {
+            ui.label(format!("暗部溢出 {:.2}%", shadows * 100.0))
+                .on_hover_text("任一通道为 0 的像素占比");
+            ui.label(format!("高光溢出 {:.2}%", highlights * 100.0))
+                .on_hover_text("任一通道为 255 的像素占比");
+        });
+    }
+
+    // ========== 新增：精确预览 ==========
+    // 在后台线程中按预览分辨率运行导出流程（缩小原图后合成），结果上传为纹理。
+    // 返回最近一次渲染完成的纹理，以及参数是否已变化（正在重新渲染）
+    fn update_accurate_preview(&mut self, ctx: &egui::Context, display_scale: f32) -> (Option<TextureHandle>, bool) {
+        let Some(original) = self.doc.original_image.clone() else {
+            return (None, false);
+        };
+        let geometry = self.doc.geometry;
+        let (width, height) = geometry.output_size(original.width(), original.height());
+
+        // 渲染比例按半个八度向上取整，缩放时不必每帧重新渲染；最高为导出分辨率，且不超过显卡纹理尺寸
+        let borders = self.frame_borders(width, height);
+        let bg_long_edge = (width + borders.left + borders.right).max(height + borders.up + borders.down) as f32;
+        let render_scale = quantize_preview_scale(display_scale)
+            .min(1.0)
+            .min(self.max_texture_side as f32 / bg_long_edge);
+
+        // 影响合成结果的参数：画框设置、几何调整、EXIF 表格、照片颜色、字体和渲染比例
+        let frame_fingerprint = self.doc.frame_fingerprint();
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        frame_fingerprint.hash(&mut hasher);
+        format!("{:?}", geometry).hash(&mut hasher);
+        for row in &self.doc.rows {
+            (&row.tag_name, &row.tag_value).hash(&mut hasher);
+        }
+        (self.doc.dominant_color, &self.doc.palette, self.doc.histogram.is_some()).hash(&mut hasher);
+        self.export_font_family.hash(&mut hasher);
+        (Arc::as_ptr(&original) as usize, self.doc.bg_texture.as_ref().map(|t| Arc::as_ptr(t) as usize)).hash(&mut hasher);
+        render_scale.to_bits().hash(&mut hasher);
+        let key = hasher.finish();
+
+        // 接收已完成的渲染结果
+        if let Some((job_key, receiver)) = &self.doc.accurate_job {
+            let job_key = *job_key;
+            match receiver.try_recv() {
+                Ok(image) => {
+                    let options = TextureOptions::LINEAR.with_mipmap_mode(Some(egui::TextureFilter::Linear));
+                    let handle = ctx.load_texture("accurate_preview", image, options);
+                    self.doc.accurate_texture = Some((job_key, handle));
+                    self.doc.accurate_job = None;
+                }
+                Err(TryRecvError::Empty) => ctx.request_repaint_after(std::time::Duration::from_millis(100)),
+                Err(TryRecvError::Disconnected) => {
+                    eprintln!("精确预览渲染线程异常退出");
+                    self.doc.accurate_texture = None;
+                    self.doc.accurate_job = None;
+                }
+            }
+        }
+
+        // 参数变化且没有正在进行的渲染时启动新的渲染（正在渲染时等它完成再开始，避免线程堆积）
+        let stale = !matches!(&self.doc.accurate_texture, Some((cached, _)) if *cached == key);
+        if stale && self.doc.accurate_job.is_none() {
+            let composition = self.frame_composition(width, height);
+            let icc = self.doc.icc_profile.clone();
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                // 先缩小原图再执行几何调整（裁剪按比例保存，与分辨率无关）
+                let scaled;
+                let source = if render_scale < 1.0 {
+                    let source_width = ((original.width() as f32 * render_scale).round() as u32).max(1);
+                    let source_height = ((original.height() as f32 * render_scale).round() as u32).max(1);
+                    scaled = original.thumbnail_exact(source_width, source_height);
+                    &scaled
+                } else {
+                    &*original
+                };
+                let edited;
+                let photo = if geometry.is_identity() {
+                    source
+                } else {
+                    edited = geometry.apply(source);
+                    &edited
+                };
+                // 预览按 sRGB 显示
+                let converted;
+                let photo = match &icc {
+                    Some(icc) => {
+                        converted = icc.to_srgb(photo);
+                        &converted
+                    }
+                    None => photo,
+                };
+                let composed = composition.render(photo, render_scale).to_rgba8();
+                let image = ColorImage::from_rgba_unmultiplied([composed.width() as usize, composed.height() as usize], &composed);
+                // 标签页已关闭时接收端被丢弃，发送失败直接忽略
+                let _ = sender.send(image);
+            });
+            self.doc.accurate_job = Some((key, receiver));
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let texture = self.doc.accurate_texture.as_ref().map(|(_, handle)| handle.clone());
+        (texture, stale)
+    }
+
+    // ========== 新增：图片预览（每个标签页各绘制一次当前文档） ==========
+    fn show_preview(&mut self, ui: &mut egui::Ui) {
+        let Some(texture) = self.doc.texture.clone() else {
+            return;
+        };
+        let ctx = ui.ctx().clone();
+
+        // 更新非纯色背景的预览纹理
+        self.update_bg_preview_texture(&ctx);
+
+        // 接收后台统计的直方图（画框中的迷你直方图随之出现）
+        if let Some(stats) = histogram::poll(&mut self.doc.histogram_job) {
+            self.doc.histogram = Some(stats);
+        } else if self.doc.histogram_job.is_some() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+
+        let total_height = ui.available_height();
+        let info_height = total_height * 0.15;
+        let image_area_height = total_height - info_height;
+
+        ui.vertical(|ui| {
+            // 图片显示区域 - 占用85%高度
+            ui.scope_builder(
+                egui::UiBuilder::new()
+                    .max_rect(egui::Rect::from_min_size(
+                        ui.available_rect_before_wrap().min,
+                        egui::vec2(ui.available_width(), image_area_height)
+                    )),  
+                |ui| {
+                    if self.crop_mode {
+                        // 裁剪范围变化时更新照片尺寸
+                        let crop = self.doc.geometry.crop;
+                        self.show_crop_toolbar(ui);
+                        self.show_crop_editor(ui, &texture);
+                        if self.doc.geometry.crop != crop {
+                            self.doc.sync_geometry();
+                        }
+                    } else {
+                        self.show_zoom_toolbar(ui);
+                        self.show_canvas(ui, &texture);
+                    }
+                }
+            );
+
+            // 在图片区域和信息区域之间添加分割线
+            ui.separator();
+
+            // 信息显示区域 - 固定在底部15%
+            ui.scope_builder(
+                egui::UiBuilder::new()
+                    .max_rect(egui::Rect::from_min_size(
+                        ui.available_rect_before_wrap().min,
+                        egui::vec2(ui.available_width(), info_height)
+                    )),
+                |ui| {
+                    // 使用垂直居中布局确保内容不被遮挡
+                    ui.vertical_centered(|ui| {
+                        // 显示图片尺寸信息
+                        ui.horizontal(|ui| {
+                            custom_text(ui, "图片尺寸：", "label", {
+                                Some(TextOptions {
+                                    size: Some(16.0),
+                                    color: Some(Vector4::new(200, 200, 200, 255)),
+                                    align: "LEFT"
+                                })
+                            });
+                            custom_text(ui, &format!("{} x {}", self.doc.image_size.x, self.doc.image_size.y), "label", {
+                                Some(TextOptions {
+                                    size: Some(16.0),
+                                    color: Some(Vector4::new(200, 200, 200, 255)),
+                                    align: "LEFT"
+                                })
+                            });
+                            // 大图使用缩小的预览纹理，导出仍使用原图
+                            let [preview_width, preview_height] = texture.size();
+                            if self.doc.source_size().is_some_and(|(width, _)| preview_width as u32 != width) {
+                                custom_text(ui, &format!("（预览 {} x {}）", preview_width, preview_height), "label", {
+                                    Some(TextOptions {
+                                        size: Some(16.0),
+                                        color: Some(Vector4::new(150, 150, 150, 255)),
+                                        align: "LEFT"
+                                    })
+                                });
+                            }
+                            // 照片内嵌非 sRGB 配置文件时显示其名称（预览已转换为 sRGB）
+                            if let Some(icc) = &self.doc.icc_profile {
+                                custom_text(ui, &format!("色彩配置文件：{}", icc.name), "label", {
+                                    Some(TextOptions {
+                                        size: Some(16.0),
+                                        color: Some(Vector4::new(150, 150, 150, 255)),
+                                        align: "LEFT"
+                                    })
+                                });
+                            }
+                        });
+
+                        // 显示文件路径信息
+                        ui.horizontal(|ui| {
+                            custom_text(ui, "文件路径：", "label", {
+                                Some(TextOptions {
+                                    size: Some(16.0),
+                                    color: Some(Vector4::new(200, 200, 200, 255)),
+                                    align: "LEFT"
+                                })
+                            });
+                            if let Some(file) = &self.doc.selected_file {
+                                // 如果路径太长，进行截断显示
+                                let display_path = if file.len() > 80 {
+                                    format!("...{}", &file[file.len()-77..])
+                                } else {
+                                    file.clone()
+                                };
+                                custom_text(ui, &display_path, "label", {
+                                    Some(TextOptions {
+                                        size: Some(16.0),
+                                        color: Some(Vector4::new(200, 200, 200, 255)),
+                                        align: "LEFT"
+                                    })
+                                });
+                            } else {
+                                custom_text(ui, "未选择文件", "label", {
+                                    Some(TextOptions {
+                                        size: Some(16.0),
+                                        color: Some(Vector4::new(200, 200, 200, 255)),
+                                        align: "LEFT"
+                                    })
+                                });
+                            }
+                        });
+                    });
+                }
+            );
+        });
+    }
+
+    // 恢复上次会话的画框设置、最近文件和导出目录
+    fn restore_session(&mut self, session: SessionState) {
+        self.apply_frame_settings(session.frame);
+        self.recent_files = session.recent_files;
+        self.last_export_dir = session.last_export_dir.filter(|dir| dir.is_dir());
+        self.preserve_icc_profile = session.preserve_icc_profile;
+        self.presets = session.presets;
+    }
+
+    // 应用一组画框设置（恢复会话、切换预设、撤销时使用），纹理路径变化时重新加载纹理背景
+    fn apply_frame_settings(&mut self, frame: FrameSettings) {
+        let texture_changed = frame.bg_texture_path != self.doc.frame.bg_texture_path || self.doc.bg_texture.is_none();
+        self.doc.frame = frame;
+        // 补齐旧版本保存的字段样式数量
+        if self.doc.frame.field_styles.len() != FrameField::ALL.len() {
+            self.doc.frame.field_styles = self.doc.frame.frame_template.field_styles();
+        }
+        if !texture_changed {
+            return;
+        }
+        self.doc.bg_texture = None;
+        self.doc.bg_texture_color = None;
+        if let Some(path) = self.doc.frame.bg_texture_path.clone()
+            && let Err(e) = self.load_bg_texture(&path)
+        {
+            eprintln!("{}", e);
+            self.doc.frame.bg_texture_path = None;
+        }
+    }
+
+    // 切换到指定预设
+    fn apply_preset(&mut self, index: usize) {
+        let Some(preset) = self.presets.get(index).cloned() else {
+            return;
+        };
+        self.history_label = Some(format!("应用预设「{}」", preset.name));
+        self.apply_frame_settings(preset.frame);
+        if let Some(family) = preset.export_font.filter(|f| self.font_library.contains_family(f)) {
+            self.export_font_family = Some(family);
+        }
+        self.selected_preset = Some(index);
+        self.preset_name = preset.name;
+    }
+
+    // 以当前设置生成预设
+    fn current_preset(&self, name: String) -> Preset {
+        Preset {
+            name,
+            frame: self.doc.frame.clone(),
+            export_font: self.export_font_family.clone(),
+        }
+    }
+
+    // ========== 新增：撤销/重做 ==========
+    fn edit_state(&self) -> EditState {
+        self.doc.edit_state()
+    }
+
+    fn restore_edit_state(&mut self, state: EditState) {
+        self.doc.rows = state.rows;
+        self.doc.geometry = state.geometry;
+        self.doc.sync_geometry();
+        self.apply_frame_settings(state.frame);
+    }
+
+    fn undo(&mut self) {
+        if let Some(state) = self.doc.history.undo().cloned() {
+            self.restore_edit_state(state);
+        }
+    }
+
+    fn redo(&mut self) {
+        if let Some(state) = self.doc.history.redo().cloned() {
+            self.restore_edit_state(state);
+        }
+    }
+
+    fn jump_to_history(&mut self, index: usize) {
+        if let Some(state) = self.doc.history.jump_to(index).cloned() {
+            self.restore_edit_state(state);
+        }
+    }
+
+    // 每帧结束时检查编辑状态，有变化则记录一条历史。
+    // 拖动滑块或输入文字期间不记录，操作结束后合并为一条记录
+    fn track_history(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input() {
+            return;
+        }
+        self.commit_history();
+    }
+
+    // 编辑状态与历史中的当前状态不同时记录一条历史
+    fn commit_history(&mut self) {
+        let label = self.history_label.take();
+        let state = self.edit_state();
+        if state != *self.doc.history.current() {
+            let label = label.unwrap_or_else(|| self.doc.history.current().describe_change(&state));
+            self.doc.history.record(label, state);
+        }
+    }
+
+    // 快捷键：Ctrl+Z 撤销，Ctrl+Shift+Z / Ctrl+Y 重做（正在输入文字时交给输入框自己处理）
+    fn handle_history_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        let redo_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND | egui::Modifiers::SHIFT, egui::Key::Z);
+        let redo_alt_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Y);
+        let undo_shortcut = egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Z);
+        // 先匹配带 Shift 的组合，避免被 Ctrl+Z 抢先消费
+        let (redo, undo) = ctx.input_mut(|i| {
+            let redo = i.consume_shortcut(&redo_shortcut) || i.consume_shortcut(&redo_alt_shortcut);
+            (redo, i.consume_shortcut(&undo_shortcut))
+        });
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+
+    fn show_toast(&mut self, message: String, is_success: bool) {
+        self.export_toast = Some(message);
+        self.export_toast_is_success = is_success;
+        self.toast_timer = Some(std::time::Instant::now());
+    }
+
+    fn session_state(&self) -> SessionState {
+        SessionState {
+            frame: self.doc.frame.clone(),
+            recent_files: self.recent_files.clone(),
+            last_export_dir: self.last_export_dir.clone(),
+            preserve_icc_profile: self.preserve_icc_profile,
+            presets: self.presets.clone(),
+        }
+    }
+
+    // 恢复保存的字体选择（字体已卸载时退回默认字体）
+    fn apply_font_settings(&mut self, settings: FontSettings) {
+        let available = |family: Option<String>| family.filter(|f| self.font_library.contains_family(f));
+        self.ui_font_family = available(settings.ui_font);
+        self.export_font_family = available(settings.export_font);
+    }
+
+    fn font_settings(&self) -> FontSettings {
+        FontSettings {
+            ui_font: self.ui_font_family.clone(),
+            export_font: self.export_font_family.clone(),
+        }
+    }
+
+    fn reset(&mut self) {
+        // 重置 EXIF 数据到当前图片的原始状态
+        self.doc.rows = self.doc.original_rows.clone();
+    }
+
+    // 重置单个字段到原始值
+    fn reset_row(&mut self, index: usize) {
+        if let (Some(row), Some(original)) = (self.doc.rows.get_mut(index), self.doc.original_rows.get(index)) {
+            row.tag_value = original.tag_value.clone();
+        }
+    }
+
+    // 返回已修改字段的序号
+    fn edited_rows(&self) -> Vec<usize> {
+        self.doc.rows
+            .iter()
+            .zip(&self.doc.original_rows)
+            .enumerate()
+            .filter(|(_, (row, original))| row.tag_value != original.tag_value)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+}
+
+// ========== 新增：图片标签页 ==========
+// 标签页只保存文档编号，绘制时从应用中取出对应的图片
+struct DocTabViewer<'a> {
+    app: &'a mut MyEguiApp,
+}
+
+impl TabViewer for DocTabViewer<'_> {
+    type Tab = u64;
+
+    fn title(&mut self, tab: &mut u64) -> egui::WidgetText {
+        self.app.doc(*tab).map(|doc| doc.title()).unwrap_or_default().into()
+    }
+
+    // 不同目录下可能有同名文件，用编号区分标签页
+    fn id(&mut self, tab: &mut u64) -> egui::Id {
+        egui::Id::new(("image_tab", *tab))
+    }
+
+    fn ui(&mut self, ui: &mut egui::Ui, tab: &mut u64) {
+        if *tab == self.app.doc.id {
+            self.app.show_preview(ui);
+            return;
+        }
+        // 分屏时未激活的图片也需要绘制：临时换入当前文档位置，绘制后换回
+        let Some(mut doc) = self.app.docs.remove(tab) else {
+            return;
+        };
+        std::mem::swap(&mut self.app.doc, &mut doc);
+        self.app.show_preview(ui);
+        std::mem::swap(&mut self.app.doc, &mut doc);
+        self.app.docs.insert(*tab, doc);
+    }
+
+    fn on_tab_button(&mut self, tab: &mut u64, response: &egui::Response) {
+        if let Some(file) = self.app.doc(*tab).and_then(|doc| doc.selected_file.as_deref()) {
+            response.clone().on_hover_text(file);
+        }
+    }
+
+    fn on_close(&mut self, tab: &mut u64) -> OnCloseResponse {
+        self.app.close_doc(*tab);
+        OnCloseResponse::Close
+    }
+
+    // 预览会自动缩放到标签页大小，不需要滚动条
+    fn scroll_bars(&self, _tab: &u64) -> [bool; 2] {
+        [false, false]
+    }
+}
+
+// 标签页右键菜单等界面文字
+fn dock_translations() -> Translations {
+    let mut translations = Translations::english();
+    translations.tab_context_menu.close_button = "关闭".to_string();
+    translations.tab_context_menu.eject_button = "在新窗口中显示".to_string();
+    translations.leaf.close_all_button = "关闭窗口".to_string();
+    translations.leaf.minimize_button = "最小化窗口".to_string();
+    translations
+}
+
+impl eframe::App for MyEguiApp {
+
+    // 保存字体选择和会话状态，下次启动时恢复（窗口大小和面板布局由 eframe 自动保存）
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, FONT_SETTINGS_KEY, &self.font_settings());
+        eframe::set_value(storage, SESSION_KEY, &self.session_state());
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+
+        self.handle_history_shortcuts(ctx);
+        self.handle_filmstrip_shortcuts(ctx);
+        self.handle_dropped_files(ctx);
+        self.show_drop_overlay(ctx);
+        self.max_texture_side = ctx.input(|i| i.max_texture_side);
+        self.poll_pending_load(ctx);
+
+        let available_width = ctx.available_rect().width(); // 使用 ctx 获取宽度
+
+        // let left_panel_width = available_width * 0.35;  // 与左侧面板的default_width保持一致
+        // 左侧可滚动面板
+        egui::SidePanel::left("left_panel")
+            .resizable(true)  // 允许调整大小
+            .default_width(available_width * 0.3)  // 默认宽度
+            .min_width(available_width * 0.3)  // 最小宽度
+            .max_width(available_width * 0.4)  // 最大宽度
+            .show(ctx, |ui| {
+
+                self.left_panel_width = ui.available_width();  // 更新实际宽度
+
+                custom_text(ui, "EXIF 信息查看器", "heading", 
+                Some(TextOptions { 
+                    size: Some(20.0), 
+                    color: Some(Vector4::new(180, 200, 150, 255)),
+                    align: "CENTER"
+                }));
+
+                ui.hyperlink_to("EXIF Viewer", "https://exifviewer.com/zh/");
+
+                ui.separator();
+
+                // 添加滚动区域
+                ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                        // 可一次选择多张图片，每张在单独的标签页中打开
+                        if ui.button("选择文件").clicked()
+                            && let Some(paths) = rfd::FileDialog::new()
+                                .add_filter("图片文件", &["png", "jpg", "jpeg", "nef"])
+                                .pick_files()
+                        {
+                            self.open_files(paths.iter().map(|path| path.display().to_string()).collect());
+                        }
+
+                        // 打开文件夹：在胶片条中浏览其中的全部图片
+                        if ui.button("打开文件夹").clicked()
+                            && let Some(folder) = rfd::FileDialog::new().pick_folder()
+                        {
+                            self.open_folder(&folder);
+                        }
+
+                        // 粘贴截图或从其他程序复制的图片，在新标签页中打开
+                        if ui.button("从剪贴板粘贴图片").clicked()
+                            && let Err(e) = self.paste_image_from_clipboard()
+                        {
+                            eprintln!("粘贴失败: {}", e);
+                            self.show_toast(format!("❌ 粘贴失败: {}", e), false);
+                        }
+
+                        // 最近打开的文件
+                        if !self.recent_files.is_empty() {
+                            let mut clicked = None;
+                            ui.collapsing("最近打开", |ui| {
+                                for file in &self.recent_files {
+                                    let name = Path::new(file)
+                                        .file_name()
+                                        .and_then(|n| n.to_str())
+                                        .unwrap_or(file);
+                                    if ui.link(name).on_hover_text(file).clicked() {
+                                        clicked = Some(file.clone());
+                                    }
+                                }
+                                if ui.button("清空列表").clicked() {
+                                    self.recent_files.clear();
+                                }
+                            });
+                            if let Some(file) = clicked {
+                                self.open_file(&file);
+                            }
+                        }
+
+                        // 原图直方图
+                        if self.doc.original_image.is_some() {
+                            ui.collapsing("直方图", |ui| self.show_histogram_panel(ui));
+                        }
+
+                        ui.separator();
+                        
+                        // 检查是否有EXIF数据
+                        let has_exif_data = self.doc.rows.iter().any(|row| !row.tag_value.is_empty());
+                        
+                        // 改进空数据提示信息
+                        if !has_exif_data {
+                            ui.centered_and_justified(|ui| {
+                                ui.label("暂无EXIF数据，请选择图片文件");
+                                ui.label("支持的格式: PNG, JPG, JPEG");
+                            });
+                            return;
+                        }
+
+                        let edited_rows = self.edited_rows();
+                        let mut reset_index = None;
+                        egui::Grid::new("exif_grid")
+                            .num_columns(3)
+                            .spacing([20.0, 8.0]) // 行、列间距
+                            .striped(true) // 斑马纹
+                            .min_col_width(40.0)
+                            .show(ui, |ui| {
+                                // 表头
+                                ui.heading(RichText::new("标签名称").size(16.0));
+                                ui.heading(RichText::new("值").size(16.0));
+                                ui.end_row();
+
+                                for (i, row) in self.doc.rows.iter_mut().enumerate() { // 每行遍历添加
+                                    let edited = edited_rows.contains(&i);
+                                    // 第一列: 固定文本（已修改的字段高亮显示）
+                                    let name = RichText::new(&row.tag_name).size(14.0);
+                                    ui.label(if edited { name.color(egui::Color32::from_rgb(255, 180, 60)) } else { name });
+                                    // 第二列: 部分可编辑
+                                    if row.tag_name == "图像宽度" || row.tag_name == "图像高度" {
+                                        ui.label(RichText::new(&row.tag_value).size(14.0));
+                                    } else {
+                                        let response = ui.text_edit_singleline(&mut row.tag_value);
+                                        if edited && let Some(original) = self.doc.original_rows.get(i) {
+                                            response.on_hover_text(format!("原始值: {}", original.tag_value));
+                                        }
+                                    }
+                                    // 第三列: 单个字段重置
+                                    if ui.add_enabled(edited, egui::Button::new("↺").small())
+                                        .on_hover_text("恢复为原始值")
+                                        .clicked()
+                                    {
+                                        reset_index = Some(i);
+                                    }
+                                    ui.end_row();
+                                }
+                            }
+                        );
+
+                        ui.separator();
+
+                        if let Some(index) = reset_index {
+                            self.history_label = Some(format!("恢复 {}", self.doc.rows[index].tag_name));
+                            self.reset_row(index);
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label(format!("总行数: {}", self.doc.rows.len()));
+                            // 重置按钮 - 恢复到当前图片加载时的EXIF数据
+                            if ui.add_enabled(!edited_rows.is_empty(), egui::Button::new("重置到初始数据")).clicked() {
+                                self.history_label = Some("重置到初始数据".to_string());
+                                self.reset(); // 使用自定义重置方法
+                            }
+                            // 添加提示文本说明重置功能
+                            ui.label(RichText::new("(恢复到当前图片加载时的数据)").small());
+                        });
+
+                        // 修改对比：列出已修改字段的原始值和当前值
+                        if !edited_rows.is_empty() {
+                            ui.collapsing(format!("修改对比（{} 项）", edited_rows.len()), |ui| {
+                                egui::Grid::new("exif_diff_grid")
+                                    .num_columns(3)
+                                    .spacing([20.0, 4.0])
+                                    .striped(true)
+                                    .show(ui, |ui| {
+                                        ui.label(RichText::new("标签名称").strong());
+                                        ui.label(RichText::new("原始值").strong());
+                                        ui.label(RichText::new("当前值").strong());
+                                        ui.end_row();
+                                        for &i in &edited_rows {
+                                            let (Some(row), Some(original)) = (self.doc.rows.get(i), self.doc.original_rows.get(i)) else {
+                                                continue;
+                                            };
+                                            ui.label(&row.tag_name);
+                                            ui.label(RichText::new(&original.tag_value).strikethrough().color(egui::Color32::from_rgb(230, 110, 110)));
+                                            ui.label(RichText::new(&row.tag_value).color(egui::Color32::from_rgb(120, 210, 120)));
+                                            ui.end_row();
+                                        }
+                                    });
+                            });
+                        }
+
+                        ui.separator();
+
+                        // ========== 新增：画框预设 ==========
+                        ui.horizontal(|ui| {
+                            ui.label("预设:");
+                            let selected_text = self.selected_preset
+                                .and_then(|i| self.presets.get(i))
+                                .map(|p| p.name.clone())
+                                .unwrap_or_else(|| "未选择".to_string());
+                            let mut chosen = None;
+                            ComboBox::from_id_salt("preset_combo")
+                                .selected_text(selected_text)
+                                .width(160.0)
+                                .show_ui(ui, |ui| {
+                                    for (i, preset) in self.presets.iter().enumerate() {
+                                        if ui.selectable_label(self.selected_preset == Some(i), &preset.name).clicked() {
+                                            chosen = Some(i);
+                                        }
+                                    }
+                                });
+                            if let Some(i) = chosen {
+                                self.apply_preset(i);
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.label("名称:");
+                            ui.text_edit_singleline(&mut self.preset_name);
+                        });
+
+                        ui.horizontal_wrapped(|ui| {
+                            let name = self.preset_name.trim().to_string();
+                            if ui.button("新建").on_hover_text("以当前设置新建预设").clicked() {
+                                let base = if name.is_empty() { "新预设" } else { name.as_str() };
+                                let preset = self.current_preset(presets::unique_name(&self.presets, base));
+                                self.preset_name = preset.name.clone();
+                                self.presets.push(preset);
+                                self.selected_preset = Some(self.presets.len() - 1);
+                            }
+
+                            let selected = self.selected_preset.filter(|i| *i < self.presets.len());
+                            ui.add_enabled_ui(selected.is_some(), |ui| {
+                                let Some(index) = selected else {
+                                    return;
+                                };
+                                if ui.button("保存").on_hover_text("用当前设置覆盖选中的预设").clicked() {
+                                    let current_name = self.presets[index].name.clone();
+                                    self.presets[index] = self.current_preset(current_name);
+                                }
+                                if ui.button("重命名").clicked() && !name.is_empty() && name != self.presets[index].name {
+                                    let others: Vec<Preset> = self.presets.iter()
+                                        .enumerate()
+                                        .filter(|(i, _)| *i != index)
+                                        .map(|(_, p)| p.clone())
+                                        .collect();
+                                    self.presets[index].name = presets::unique_name(&others, &name);
+                                    self.preset_name = self.presets[index].name.clone();
+                                }
+                                if ui.button("复制").clicked() {
+                                    let mut copy = self.presets[index].clone();
+                                    copy.name = presets::unique_name(&self.presets, &format!("{} 副本", copy.name));
+                                    self.preset_name = copy.name.clone();
+                                    self.presets.insert(index + 1, copy);
+                                    self.selected_preset = Some(index + 1);
+                                }
+                                if ui.button("删除").clicked() {
+                                    self.presets.remove(index);
+                                    self.selected_preset = None;
+                                }
+                            });
+
+                            if ui.button("导入").on_hover_text("从 JSON 文件导入预设").clicked()
+                                && let Some(path) = rfd::FileDialog::new()
+                                    .add_filter("预设文件", &["json"])
+                                    .pick_file()
+                            {
+                                match presets::load_from_file(&path) {
+                                    Ok(imported) => {
+                                        let count = imported.len();
+                                        for mut preset in imported {
+                                            preset.name = presets::unique_name(&self.presets, &preset.name);
+                                            self.presets.push(preset);
+                                        }
+                                        self.show_toast(format!("✅ 已导入 {} 个预设", count), true);
+                                    }
+                                    Err(e) => self.show_toast(format!("❌ 导入失败: {}", e), false),
+                                }
+                            }
+                            if ui.button("导出").on_hover_text("将所有预设导出为 JSON 文件，便于共享").clicked()
+                                && let Some(path) = rfd::FileDialog::new()
+                                    .set_file_name("exif_frame_presets.json")
+                                    .add_filter("预设文件", &["json"])
+                                    .save_file()
+                            {
+                                match presets::save_to_file(&path, &self.presets) {
+                                    Ok(()) => self.show_toast("✅ 预设已导出".to_string(), true),
+                                    Err(e) => self.show_toast(format!("❌ 导出失败: {}", e), false),
+                                }
+                            }
+                        });
+
+                        ui.separator();
+
+                        // ========== 新增：编辑历史面板 ==========
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(self.doc.history.can_undo(), egui::Button::new("↶ 撤销"))
+                                .on_hover_text("Ctrl+Z")
+                                .clicked()
+                            {
+                                self.undo();
+                            }
+                            if ui.add_enabled(self.doc.history.can_redo(), egui::Button::new("↷ 重做"))
+                                .on_hover_text("Ctrl+Shift+Z")
+                                .clicked()
+                            {
+                                self.redo();
+                            }
+                        });
+                        ui.collapsing("编辑历史", |ui| {
+                            let mut jump = None;
+                            ScrollArea::vertical()
+                                .id_salt("history_scroll")
+                                .max_height(160.0)
+                                .show(ui, |ui| {
+                                    let position = self.doc.history.position();
+                                    for (i, label) in self.doc.history.labels().enumerate() {
+                                        // 当前位置之后的记录为可重做的操作，用灰色显示
+                                        let text = if i > position {
+                                            RichText::new(label).weak()
+                                        } else {
+                                            RichText::new(label)
+                                        };
+                                        if ui.selectable_label(i == position, text).clicked() {
+                                            jump = Some(i);
+                                        }
+                                    }
+                                });
+                            if let Some(index) = jump {
+                                self.jump_to_history(index);
+                            }
+                        });
+
+                        ui.separator();
+
+                        // ========== 新增：旋转、翻转、拉直和裁剪（不修改原图，导出时执行） ==========
+                        ui.add_enabled_ui(self.doc.original_image.is_some(), |ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                ui.label("旋转:");
+                                let actions = [
+                                    ("向左 90°", "向左旋转"),
+                                    ("向右 90°", "向右旋转"),
+                                    ("水平翻转", "水平翻转"),
+                                    ("垂直翻转", "垂直翻转"),
+                                ];
+                                for (i, (text, label)) in actions.into_iter().enumerate() {
+                                    if ui.button(text).clicked() {
+                                        self.history_label = Some(label.to_string());
+                                        match i {
+                                            0 => self.doc.geometry.rotate(false),
+                                            1 => self.doc.geometry.rotate(true),
+                                            2 => self.doc.geometry.flip(true),
+                                            _ => self.doc.geometry.flip(false),
+                                        }
+                                        self.doc.sync_geometry();
+                                    }
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("拉直:");
+                                let max = geometry::MAX_STRAIGHTEN;
+                                if ui.add(egui::Slider::new(&mut self.doc.geometry.straighten, -max..=max).step_by(0.1).suffix("°")).changed() {
+                                    self.doc.sync_geometry();
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.toggle_value(&mut self.crop_mode, "裁剪").on_hover_text("在预览中拖动裁剪框调整裁剪范围");
+                                if ui.add_enabled(!self.doc.geometry.is_identity(), egui::Button::new("重置"))
+                                    .on_hover_text("清除旋转、翻转、拉直和裁剪")
+                                    .clicked()
+                                {
+                                    self.history_label = Some("重置几何调整".to_string());
+                                    self.doc.geometry = Geometry::default();
+                                    self.doc.sync_geometry();
+                                }
+                            });
+                        });
+
+                        ui.separator();
 
-impl eframe::App for MyEguiApp {
-    
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+                        // 边框模式选择
+                        ui.horizontal(|ui| {
+                            ui.label("边框模式:");
+                            ComboBox::from_id_salt("border_mode")
+                                .selected_text(self.doc.frame.border_mode.label())
+                                .width(100.0)
+                                .show_ui(ui, |ui| {
+                                    for mode in [BorderMode::Pixel, BorderMode::Percent, BorderMode::AspectRatio] {
+                                        ui.selectable_value(&mut self.doc.frame.border_mode, mode, mode.label());
+                                    }
+                                });
+                        });
 
-        let available_width = ctx.available_rect().width(); // 使用 ctx 获取宽度
-        let available_height = ctx.available_rect().height(); // 使用 ctx 获取高度
+                        if self.doc.frame.border_mode == BorderMode::Pixel {
+                            // 主要的数值输入框
+                            egui::Grid::new("bg_color_grid")
+                                .num_columns(4)
+                                .spacing([20.0, 10.0])
+                                .show(ui, |ui| {
+
+                                ui.label("上:");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.doc.frame.up_value)
+                                        .range(self.doc.frame.min_value..=self.doc.frame.max_value)             // 设置数值范围
+                                        .speed(self.doc.frame.step)                                   // 设置调整速度
+                                        .fixed_decimals(self.doc.frame.decimal_places)  // 固定小数位数
+                                );
+                                ui.label("下:");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.doc.frame.down_value)
+                                        .range(self.doc.frame.min_value..=self.doc.frame.max_value)
+                                        .speed(self.doc.frame.step)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                );
+                                ui.end_row();
 
-        // let left_panel_width = available_width * 0.35;  // 与左侧面板的default_width保持一致
-        // 左侧可滚动面板
-        egui::SidePanel::left("left_panel")
-            .resizable(true)  // 允许调整大小
-            .default_width(available_width * 0.3)  // 默认宽度
-            .min_width(available_width * 0.3)  // 最小宽度
-            .max_width(available_width * 0.4)  // 最大宽度
-            .show(ctx, |ui| {
+                                ui.label("左:");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.doc.frame.left_value)
+                                        .range(self.doc.frame.min_value..=self.doc.frame.max_value)
+                                        .speed(self.doc.frame.step)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                );
+                                ui.label("右:");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.doc.frame.right_value)
+                                        .range(self.doc.frame.min_value..=self.doc.frame.max_value)
+                                        .speed(self.doc.frame.step)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                );
+                            });
+                        } else if self.doc.frame.border_mode == BorderMode::Percent {
+                            ui.horizontal(|ui| {
+                                ui.label("参照边:");
+                                ui.radio_value(&mut self.doc.frame.percent_base, PercentBase::ShortEdge, "短边");
+                                ui.radio_value(&mut self.doc.frame.percent_base, PercentBase::LongEdge, "长边");
+                            });
+                            egui::Grid::new("percent_grid")
+                                .num_columns(4)
+                                .spacing([20.0, 10.0])
+                                .show(ui, |ui| {
+                                    fn percent_edit(value: &mut f32, decimals: usize) -> egui::DragValue<'_> {
+                                        egui::DragValue::new(value)
+                                            .range(0.0..=100.0)
+                                            .speed(0.1)
+                                            .fixed_decimals(decimals)
+                                            .suffix("%")
+                                    }
+                                    let decimals = self.doc.frame.decimal_places;
+                                    ui.label("上:");
+                                    ui.add(percent_edit(&mut self.doc.frame.up_percent, decimals));
+                                    ui.label("下:");
+                                    ui.add(percent_edit(&mut self.doc.frame.down_percent, decimals));
+                                    ui.end_row();
 
-                self.left_panel_width = ui.available_width();  // 更新实际宽度
+                                    ui.label("左:");
+                                    ui.add(percent_edit(&mut self.doc.frame.left_percent, decimals));
+                                    ui.label("右:");
+                                    ui.add(percent_edit(&mut self.doc.frame.right_percent, decimals));
+                                    ui.end_row();
+                                });
+                            // 显示换算后的像素值，便于和导出结果对照
+                            if self.doc.texture.is_some() {
+                                let borders = self.frame_borders(self.doc.image_size.x as u32, self.doc.image_size.y as u32);
+                                ui.label(format!(
+                                    "折合像素: 上{} 下{} 左{} 右{}",
+                                    borders.up, borders.down, borders.left, borders.right
+                                ));
+                            }
+                        } else {
+                            egui::Grid::new("canvas_grid")
+                                .num_columns(2)
+                                .spacing([20.0, 10.0])
+                                .show(ui, |ui| {
+                                    ui.label("目标比例:");
+                                    ui.horizontal(|ui| {
+                                        ComboBox::from_id_salt("canvas_ratio")
+                                            .selected_text(self.doc.frame.canvas_ratio.label())
+                                            .width(80.0)
+                                            .show_ui(ui, |ui| {
+                                                for ratio in CanvasRatio::ALL {
+                                                    ui.selectable_value(&mut self.doc.frame.canvas_ratio, ratio, ratio.label());
+                                                }
+                                            });
+                                        ui.checkbox(&mut self.doc.frame.canvas_ratio_swapped, "交换宽高");
+                                    });
+                                    ui.end_row();
 
-                custom_text(ui, "EXIF 信息查看器", "heading", 
-                Some(TextOptions { 
-                    size: Some(20.0), 
-                    color: Some(Vector4::new(180, 200, 150, 255)),
-                    align: "CENTER"
-                }));
+                                    ui.label("最小留白(长边%):");
+                                    ui.add(
+                                        egui::DragValue::new(&mut self.doc.frame.canvas_margin_percent)
+                                            .range(0.0..=50.0)
+                                            .speed(0.1)
+                                            .fixed_decimals(self.doc.frame.decimal_places)
+                                            .suffix("%")
+                                    );
+                                    ui.end_row();
 
-                ui.hyperlink_to("EXIF Viewer", "https://exifviewer.com/zh/");
+                                    ui.label("水平偏移:");
+                                    ui.add(egui::Slider::new(&mut self.doc.frame.canvas_offset_x, -1.0..=1.0).text("左 ← → 右"));
+                                    ui.end_row();
 
-                ui.separator();
+                                    ui.label("垂直偏移:");
+                                    ui.add(egui::Slider::new(&mut self.doc.frame.canvas_offset_y, -1.0..=1.0).text("上 ← → 下"));
+                                    ui.end_row();
 
-                // 添加滚动区域
-                ScrollArea::vertical()
-                    .auto_shrink([false; 2])
-                    .show(ui, |ui| {
-                        if ui.button("选择文件").clicked() {
-                            if let Some(path) = rfd::FileDialog::new()
-                                .add_filter("图片文件", &["png", "jpg", "jpeg", "nef"])
-                                .pick_file() 
-                            {
-                                let file_path = path.display().to_string();
-                                self.selected_file = Some(file_path.clone());
-                                        
-                                // 加载选中的图片
-                                if let Err(e) = self.load_image(ctx, &file_path) {
-                                    eprintln!("Fail to load pictures: {}", e);
-                                    self.texture = None;
-                                }
-                            }
+                                    if ui.button("居中").clicked() {
+                                        self.doc.frame.canvas_offset_x = 0.0;
+                                        self.doc.frame.canvas_offset_y = 0.0;
+                                    }
+                                    // 显示计算出的画布尺寸
+                                    if self.doc.texture.is_some() {
+                                        let (width, height) = (self.doc.image_size.x as u32, self.doc.image_size.y as u32);
+                                        let borders = self.frame_borders(width, height);
+                                        ui.label(format!(
+                                            "画布: {} x {} (上{} 下{} 左{} 右{})",
+                                            width + borders.left + borders.right,
+                                            height + borders.up + borders.down,
+                                            borders.up, borders.down, borders.left, borders.right
+                                        ));
+                                    }
+                                    ui.end_row();
+                                });
                         }
-
-                        ui.separator();
-                        
-                        // 检查是否有EXIF数据
-                        let has_exif_data = self.rows.iter().any(|row| !row.tag_value.is_empty());
                         
-                        // 改进空数据提示信息
-                        if !has_exif_data {
-                            ui.centered_and_justified(|ui| {
-                                ui.label("暂无EXIF数据，请选择图片文件");
-                                ui.label("支持的格式: PNG, JPG, JPEG");
-                            });
-                            return;
-                        }
+                        ui.separator();
 
-                        egui::Grid::new("exif_grid")
-                            .num_columns(2)
-                            .spacing([20.0, 8.0]) // 行、列间距
-                            .striped(true) // 斑马纹
-                            .min_col_width(40.0)
+                        // 圆角与投影
+                        egui::Grid::new("photo_effect_grid")
+                            .num_columns(4)
+                            .spacing([20.0, 8.0])
                             .show(ui, |ui| {
-                                // 表头
-                                ui.heading(RichText::new("标签名称").size(16.0));
-                                ui.heading(RichText::new("值").size(16.0));
+                                ui.label("圆角(短边%):");
+                                ui.add(
+                                    egui::DragValue::new(&mut self.doc.frame.corner_radius_percent)
+                                        .range(0.0..=50.0)
+                                        .speed(0.1)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                        .suffix("%")
+                                );
+                                ui.label("投影:");
+                                ui.checkbox(&mut self.doc.frame.shadow_enabled, "");
                                 ui.end_row();
 
-                                for (i, row) in self.rows.iter_mut().enumerate() { // 每行遍历添加
-                                    // 第一列: 固定文本
-                                    ui.label(RichText::new(&row.tag_name).size(14.0));
-                                    // 第二列: 部分可编辑
-                                    if row.tag_name == "图像宽度" || row.tag_name == "图像高度" {
-                                        ui.label(RichText::new(&row.tag_value).size(14.0));
-                                    } else {
-                                        ui.text_edit_singleline(&mut row.tag_value);
-                                    }
-                                    ui.end_row();
-                                }
-                            }
-                        );
+                                ui.add_enabled_ui(self.doc.frame.shadow_enabled, |ui| {
+                                    ui.label("偏移X(%):");
+                                });
+                                ui.add_enabled(
+                                    self.doc.frame.shadow_enabled,
+                                    egui::DragValue::new(&mut self.doc.frame.shadow_offset_x_percent)
+                                        .range(-20.0..=20.0)
+                                        .speed(0.1)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                );
+                                ui.add_enabled_ui(self.doc.frame.shadow_enabled, |ui| {
+                                    ui.label("偏移Y(%):");
+                                });
+                                ui.add_enabled(
+                                    self.doc.frame.shadow_enabled,
+                                    egui::DragValue::new(&mut self.doc.frame.shadow_offset_y_percent)
+                                        .range(-20.0..=20.0)
+                                        .speed(0.1)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                );
+                                ui.end_row();
+
+                                ui.add_enabled_ui(self.doc.frame.shadow_enabled, |ui| {
+                                    ui.label("模糊(%):");
+                                });
+                                ui.add_enabled(
+                                    self.doc.frame.shadow_enabled,
+                                    egui::DragValue::new(&mut self.doc.frame.shadow_blur_percent)
+                                        .range(0.0..=20.0)
+                                        .speed(0.1)
+                                        .fixed_decimals(self.doc.frame.decimal_places)
+                                );
+                                ui.add_enabled_ui(self.doc.frame.shadow_enabled, |ui| {
+                                    ui.label("不透明度:");
+                                });
+                                ui.add_enabled(
+                                    self.doc.frame.shadow_enabled,
+                                    egui::Slider::new(&mut self.doc.frame.shadow_opacity, 0.0..=1.0)
+                                );
+                                ui.end_row();
+                            });
 
                         ui.separator();
 
+                        // 界面字体与导出字体分开选择，下次启动时恢复
+                        let families = self.font_library.families();
+                        egui::Grid::new("font_grid")
+                            .num_columns(2)
+                            .spacing([20.0, 8.0])
+                            .show(ui, |ui| {
+                                ui.label("界面字体:");
+                                if font_family_combo(ui, "ui_font_combo", &mut self.ui_font_family, &families, "默认中文字体") {
+                                    setup_fonts_and_style(ctx, &self.font_library, self.ui_font_family.as_deref());
+                                }
+                                ui.end_row();
+
+                                ui.label("导出字体:");
+                                font_family_combo(ui, "export_font_combo", &mut self.export_font_family, &families, "默认中文字体");
+                                ui.end_row();
+                            });
+
+                        // 画框文字风格：选择风格会重置各字段样式，之后可逐个字段调整
                         ui.horizontal(|ui| {
-                            ui.label(format!("总行数: {}", self.rows.len()));
-                            // 重置按钮 - 恢复到第一次加载图片时的EXIF数据
-                            if ui.button("重置到初始数据").clicked() {
-                                self.reset(); // 使用自定义重置方法
+                            ui.label("文字风格:");
+                            ComboBox::from_id_salt("frame_template_combo")
+                                .selected_text(self.doc.frame.frame_template.label())
+                                .show_ui(ui, |ui| {
+                                    for template in FrameTemplate::ALL {
+                                        if ui.selectable_value(&mut self.doc.frame.frame_template, template, template.label()).clicked() {
+                                            self.doc.frame.field_styles = template.field_styles();
+                                        }
+                                    }
+                                });
+                            if ui.button("恢复风格默认").clicked() {
+                                self.doc.frame.field_styles = self.doc.frame.frame_template.field_styles();
                             }
-                            // 添加提示文本说明重置功能
-                            ui.label(RichText::new("(恢复到第一次加载的数据)").small());
                         });
 
-                        ui.separator();
+                        egui::Grid::new("field_style_grid")
+                            .num_columns(5)
+                            .spacing([12.0, 8.0])
+                            .show(ui, |ui| {
+                                ui.label("字段");
+                                ui.label("字体");
+                                ui.label("字形");
+                                ui.label("字号倍数");
+                                ui.label("颜色");
+                                ui.end_row();
 
+                                for (i, (field, style)) in FrameField::ALL.iter().zip(self.doc.frame.field_styles.iter_mut()).enumerate() {
+                                    ui.label(field.tag_name());
 
-                        // 主要的数值输入框
-                        egui::Grid::new("bg_color_grid")
-                            .num_columns(4)
-                            .spacing([20.0, 10.0])
-                            .show(ui, |ui| {
+                                    if font_family_combo(ui, ("field_font_combo", i), &mut style.font_family, &families, "跟随导出字体") {
+                                        style.font_style = None;
+                                    }
+
+                                    // 字形：按字重自动匹配，或指定字体族中的某个样式（如 .ttc 集合中的具体字体）
+                                    let face_styles = style.font_family.as_deref()
+                                        .map(|family| self.font_library.styles(family))
+                                        .unwrap_or_default();
+                                    ComboBox::from_id_salt(("field_weight_combo", i))
+                                        .selected_text(style.font_style.clone().unwrap_or_else(|| style.weight.label().to_string()))
+                                        .show_ui(ui, |ui| {
+                                            for weight in FontWeight::ALL {
+                                                if ui.selectable_label(style.font_style.is_none() && style.weight == weight, weight.label()).clicked() {
+                                                    style.weight = weight;
+                                                    style.font_style = None;
+                                                }
+                                            }
+                                            if !face_styles.is_empty() {
+                                                ui.separator();
+                                            }
+                                            for face_style in face_styles {
+                                                let selected = style.font_style.as_deref() == Some(face_style.as_str());
+                                                if ui.selectable_label(selected, &face_style).clicked() {
+                                                    style.font_style = Some(face_style);
+                                                }
+                                            }
+                                        });
+
+                                    ui.add(
+                                        egui::DragValue::new(&mut style.size_ratio)
+                                            .range(0.2..=2.0)
+                                            .speed(0.01)
+                                            .fixed_decimals(2)
+                                    );
+
+                                    ui.horizontal(|ui| {
+                                        let mut auto = style.color.is_none();
+                                        if ui.checkbox(&mut auto, "跟随").changed() {
+                                            style.color = if auto { None } else { Some(self.doc.frame.text_color.unwrap_or(egui::Color32::BLACK)) };
+                                        }
+                                        if let Some(color) = &mut style.color {
+                                            egui::color_picker::color_edit_button_srgba(ui, color, egui::color_picker::Alpha::Opaque);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
 
-                            ui.label("上:");
-                            ui.add(
-                                egui::DragValue::new(&mut self.up_value)
-                                    .range(self.min_value..=self.max_value)             // 设置数值范围
-                                    .speed(self.step)                                   // 设置调整速度
-                                    .fixed_decimals(self.decimal_places)  // 固定小数位数
-                            );
-                            ui.label("下:");
-                            ui.add(
-                                egui::DragValue::new(&mut self.down_value)
-                                    .range(self.min_value..=self.max_value)
-                                    .speed(self.step)
-                                    .fixed_decimals(self.decimal_places)
-                            );
-                            ui.end_row();
-
-                            ui.label("左:");
-                            ui.add(
-                                egui::DragValue::new(&mut self.left_value)
-                                    .range(self.min_value..=self.max_value)
-                                    .speed(self.step)
-                                    .fixed_decimals(self.decimal_places)
-                            );
-                            ui.label("右:");
-                            ui.add(
-                                egui::DragValue::new(&mut self.right_value)
-                                    .range(self.min_value..=self.max_value)
-                                    .speed(self.step)
-                                    .fixed_decimals(self.decimal_places)
-                            );
-                        });
-                        
                         ui.separator();
 
                         egui::Grid::new("config_grid")
@@ -1303,34 +4268,92 @@ impl eframe::App for MyEguiApp {
                             .spacing([20.0, 8.0])
                             .show(ui, |ui| {
                                 ui.label("最小值:");
-                                ui.add(egui::DragValue::new(&mut self.min_value).speed(0.1));
+                                ui.add(egui::DragValue::new(&mut self.doc.frame.min_value).speed(0.1));
                                 
                                 ui.label("最大值:");
-                                ui.add(egui::DragValue::new(&mut self.max_value).speed(1));
+                                ui.add(egui::DragValue::new(&mut self.doc.frame.max_value).speed(1));
                                 ui.end_row();
                                 
                                 ui.label("步长:");
                                 ComboBox::from_label(".")
-                                    .selected_text(format!("{:.1}", self.step)) // 显示当前选中的步长
+                                    .selected_text(format!("{:.1}", self.doc.frame.step)) // 显示当前选中的步长
                                     .width(80.0) // 下拉框宽度
                                     .show_ui(ui, |ui| {
-                                        ui.selectable_value(&mut self.step, 0.1, "0.1");
-                                        ui.selectable_value(&mut self.step, 1.0, "1.0");
+                                        ui.selectable_value(&mut self.doc.frame.step, 0.1, "0.1");
+                                        ui.selectable_value(&mut self.doc.frame.step, 1.0, "1.0");
                                     });
                                 
                                 ui.label("小数位数:");
-                                ui.add(egui::DragValue::new(&mut self.decimal_places).range(0..=2).speed(1));
+                                ui.add(egui::DragValue::new(&mut self.doc.frame.decimal_places).range(0..=2).speed(1));
+                                ui.end_row();
+
+                                ui.label("背景类型:");
+                                ComboBox::from_id_salt("bg_kind")
+                                    .selected_text(self.doc.frame.bg_kind.label())
+                                    .width(80.0)
+                                    .show_ui(ui, |ui| {
+                                        for kind in BackgroundKind::ALL {
+                                            ui.selectable_value(&mut self.doc.frame.bg_kind, kind, kind.label());
+                                        }
+                                    });
+                                match self.doc.frame.bg_kind {
+                                    BackgroundKind::LinearGradient | BackgroundKind::RadialGradient => {
+                                        ui.label("结束颜色:");
+                                        ui.horizontal(|ui| {
+                                            egui::color_picker::color_edit_button_srgba(
+                                                ui,
+                                                &mut self.doc.frame.bg_gradient_end,
+                                                egui::color_picker::Alpha::Opaque,
+                                            );
+                                            if self.doc.frame.bg_kind == BackgroundKind::LinearGradient {
+                                                ui.add(
+                                                    egui::DragValue::new(&mut self.doc.frame.bg_gradient_angle)
+                                                        .range(0.0..=360.0)
+                                                        .speed(1.0)
+                                                        .suffix("°")
+                                                );
+                                            }
+                                        });
+                                    }
+                                    BackgroundKind::DominantColor => {
+                                        ui.label("主色:");
+                                        match self.doc.dominant_color {
+                                            Some(color) => {
+                                                let (rect, _) = ui.allocate_exact_size(egui::vec2(40.0, 16.0), egui::Sense::hover());
+                                                ui.painter().rect_filled(rect, 2.0, color);
+                                            }
+                                            None => {
+                                                ui.label("需先加载图片");
+                                            }
+                                        }
+                                    }
+                                    BackgroundKind::Texture => {
+                                        if ui.button("选择纹理").clicked()
+                                            && let Some(path) = rfd::FileDialog::new()
+                                                .add_filter("图片文件", &["png", "jpg", "jpeg"])
+                                                .pick_file()
+                                            && let Err(e) = self.load_bg_texture(&path.display().to_string())
+                                        {
+                                            eprintln!("{}", e);
+                                        }
+                                        ui.horizontal(|ui| {
+                                            ui.radio_value(&mut self.doc.frame.bg_texture_fit, compose::TextureFit::Tile, "平铺");
+                                            ui.radio_value(&mut self.doc.frame.bg_texture_fit, compose::TextureFit::Stretch, "拉伸");
+                                        });
+                                    }
+                                    BackgroundKind::Solid => {}
+                                }
                                 ui.end_row();
 
                                 ui.label("背景色：");
                                 ComboBox::from_label("")
-                                    .selected_text(format!("#{:02X}{:02X}{:02X}", self.bg_color.r(),self.bg_color.b(),self.bg_color.g())) // 显示当前选中的背景色
+                                    .selected_text(format!("#{:02X}{:02X}{:02X}", self.doc.frame.bg_color.r(),self.doc.frame.bg_color.b(),self.doc.frame.bg_color.g())) // 显示当前选中的背景色
                                     .width(80.0) // 下拉框宽度
                                     .show_ui(ui, |ui| {
-                                        if ui.selectable_value(&mut self.bg_color, egui::Color32::WHITE, "白色").clicked() {
+                                        if ui.selectable_value(&mut self.doc.frame.bg_color, egui::Color32::WHITE, "白色").clicked() {
                                             self.show_custom_bg_color_picker = false;
                                         };
-                                        if ui.selectable_value(&mut self.bg_color, egui::Color32::BLACK, "黑色").clicked() {
+                                        if ui.selectable_value(&mut self.doc.frame.bg_color, egui::Color32::BLACK, "黑色").clicked() {
                                             self.show_custom_bg_color_picker = false;
                                         };
                                         if ui.selectable_label(false, "自定义").clicked() {
@@ -1341,33 +4364,91 @@ impl eframe::App for MyEguiApp {
                                     ui.horizontal(|ui| {
                                         egui::color_picker::color_edit_button_srgba(
                                             ui,
-                                            &mut self.bg_color, // 直接绑定到背景色，实时修改
+                                            &mut self.doc.frame.bg_color, // 直接绑定到背景色，实时修改
                                             egui::color_picker::Alpha::Opaque, // 仅不透明颜色
                                         );
                                     });
                                 }
                                 ui.end_row();
 
+                                // 调色板色块：左键设为背景，右键选择用途
+                                ui.label("照片色板:");
+                                ui.horizontal(|ui| {
+                                    if self.doc.palette.is_empty() {
+                                        ui.label("需先加载图片");
+                                    }
+                                    for color in self.doc.palette.clone() {
+                                        let (rect, response) = ui.allocate_exact_size(egui::vec2(20.0, 20.0), egui::Sense::click());
+                                        ui.painter().rect_filled(rect, 2.0, color);
+                                        if response.hovered() {
+                                            ui.painter().rect_stroke(rect, 2.0, egui::Stroke::new(1.0, egui::Color32::WHITE), egui::StrokeKind::Outside);
+                                        }
+                                        let response = response.on_hover_text(format!(
+                                            "#{:02X}{:02X}{:02X}\n左键：设为背景\n右键：更多选项",
+                                            color.r(), color.g(), color.b()
+                                        ));
+                                        if response.clicked() {
+                                            self.doc.frame.bg_kind = BackgroundKind::Solid;
+                                            self.doc.frame.bg_color = color;
+                                        }
+                                        response.context_menu(|ui| {
+                                            if ui.button("设为背景").clicked() {
+                                                self.doc.frame.bg_kind = BackgroundKind::Solid;
+                                                self.doc.frame.bg_color = color;
+                                                ui.close();
+                                            }
+                                            if ui.button("设为渐变结束色").clicked() {
+                                                self.doc.frame.bg_gradient_end = color;
+                                                ui.close();
+                                            }
+                                            if ui.button("设为文字颜色").clicked() {
+                                                self.doc.frame.text_color = Some(color);
+                                                ui.close();
+                                            }
+                                        });
+                                    }
+                                });
+                                ui.label("色板条:");
+                                ui.checkbox(&mut self.doc.frame.show_palette_strip, "在画框中显示");
+                                ui.end_row();
+
+                                ui.label("直方图:");
+                                ui.checkbox(&mut self.doc.frame.show_histogram, "在画框中显示")
+                                    .on_hover_text("在文字区域左侧绘制照片的亮度直方图");
+                                ui.end_row();
+
+                                ui.label("文字颜色:");
+                                ui.horizontal(|ui| {
+                                    let mut auto = self.doc.frame.text_color.is_none();
+                                    if ui.checkbox(&mut auto, "自动").changed() {
+                                        self.doc.frame.text_color = if auto { None } else { Some(self.resolved_text_color()) };
+                                    }
+                                    if let Some(color) = &mut self.doc.frame.text_color {
+                                        egui::color_picker::color_edit_button_srgba(ui, color, egui::color_picker::Alpha::Opaque);
+                                    }
+                                });
+                                ui.end_row();
+
                                 ui.label("启用模糊背景:");
-                                ui.checkbox(&mut self.enable_blur_bg, ""); // 复选框控制是否启用
+                                ui.checkbox(&mut self.doc.frame.enable_blur_bg, ""); // 复选框控制是否启用
 
                                 ui.label("模糊强度:");
-                                let mut blur_enabled = self.enable_blur_bg && self.texture.is_some();
+                                let blur_enabled = self.doc.frame.enable_blur_bg && self.doc.texture.is_some();
                                 let blur_response = ui.add_enabled(
                                     blur_enabled,
-                                    egui::DragValue::new(&mut self.blur_strength)
+                                    egui::DragValue::new(&mut self.doc.frame.blur_strength)
                                         .range(0.1..=5.0) // 模糊强度范围
                                         .speed(0.1)
                                 );
                                 if blur_response.changed() {
-                                    self.blur_strength = self.blur_strength.clamp(0.1, 5.0);
+                                    self.doc.frame.blur_strength = self.doc.frame.blur_strength.clamp(0.1, 5.0);
                                 }
                                 ui.end_row();
 
                                 // 图片加载状态提示
-                                if self.texture.is_none() {
+                                if self.doc.texture.is_none() {
                                     ui.label(egui::RichText::new("图片未加载").color(egui::Color32::RED));
-                                } else if !self.enable_blur_bg {
+                                } else if !self.doc.frame.enable_blur_bg {
                                     ui.label(egui::RichText::new("已加载图片").color(egui::Color32::GREEN));
                                 } else {
                                     ui.label(egui::RichText::new("模糊生效中").color(egui::Color32::BLUE));
@@ -1377,285 +4458,82 @@ impl eframe::App for MyEguiApp {
 
                         ui.separator();
 
+                        // 打开多张图片时，可把当前画框设置应用到全部标签页，统一风格后对比
+                        if !self.docs.is_empty() && ui.button("同步画框设置到全部标签页").clicked() {
+                            self.sync_frame_to_all_docs();
+                        }
+
+                        // 照片内嵌广色域配置文件时可选择导出方式：转换为 sRGB 兼容性最好，保留原配置文件不损失色域
+                        ui.checkbox(&mut self.preserve_icc_profile, "保留原始色彩配置文件")
+                            .on_hover_text("照片内嵌非 sRGB 配置文件（如 Display P3、Adobe RGB）时，导出图片沿用该配置文件；\n不勾选时转换为 sRGB");
+
                         if ui.button("导出图片").clicked() {
                             match self.export_combined_image() {
-                                Ok(_) => {
+                                Ok(save_path) => {
+                                    // 记住导出目录，下次导出时默认打开
+                                    self.last_export_dir = save_path.parent().map(|dir| dir.to_path_buf());
                                     // 设置成功提示
-                                    self.export_toast = Some("✅ 导出成功！".to_string());
-                                    self.export_toast_is_success = true;
+                                    self.show_toast("✅ 导出成功！".to_string(), true);
                                 }
                                 Err(e) => {
                                     eprintln!("导出失败: {}", e);
                                     // 设置失败提示
-                                    self.export_toast = Some(format!("❌ 导出失败: {}", e));
-                                    self.export_toast_is_success = false;
+                                    self.show_toast(format!("❌ 导出失败: {}", e), false);
                                 }
                             }
                         }
+
+                        // 不弹出保存对话框，直接把画框图片或 EXIF 文字复制到剪贴板（可粘贴到聊天软件）
+                        ui.horizontal(|ui| {
+                            if ui.button("复制图片").on_hover_text("把合成的画框图片复制到剪贴板").clicked() {
+                                match self.copy_image_to_clipboard() {
+                                    Ok(()) => self.show_toast("✅ 已复制图片到剪贴板".to_string(), true),
+                                    Err(e) => {
+                                        eprintln!("复制图片失败: {}", e);
+                                        self.show_toast(format!("❌ 复制失败: {}", e), false);
+                                    }
+                                }
+                            }
+                            if ui.button("复制EXIF文字").on_hover_text("把 EXIF 信息按行复制到剪贴板").clicked() {
+                                match self.copy_exif_to_clipboard() {
+                                    Ok(()) => self.show_toast("✅ 已复制EXIF信息到剪贴板".to_string(), true),
+                                    Err(e) => self.show_toast(format!("❌ 复制失败: {}", e), false),
+                                }
+                            }
+                        });
                     }
                 );
             }
         );
 
+        // 底部缩略图胶片条（位于预览区下方）
+        self.show_filmstrip(ctx);
+
         // 右侧固定面板
         egui::CentralPanel::default()
             .show(ctx, |ui| {
-                if let Some(texture) = &self.texture {
-                    let total_height = available_height;
-                    let info_height = total_height * 0.15;
-                    let image_area_height = total_height - info_height;
-                    
-                    // 垂直布局，先显示标题和分隔线
-                    ui.vertical(|ui| {
-                        custom_text(ui, "图片预览:", "heading",
-                        Some(TextOptions {
-                            size: Some(24.0),
-                            color: None,
-                            align: "LEFT",
-                        }));
-                        ui.separator();
-                        
-                        // 图片显示区域 - 占用85%高度
-                        ui.scope_builder(
-                            egui::UiBuilder::new()
-                                .max_rect(egui::Rect::from_min_size(
-                                    ui.available_rect_before_wrap().min,
-                                    egui::vec2(ui.available_width(), image_area_height)
-                                )),  
-                            |ui| {
-                                ui.centered_and_justified(|ui| {
-
-                                    let (img_width, img_height) = if self.image_size.x > self.image_size.y {
-                                        let img_width = ui.available_width();
-                                        let img_ratio = self.image_size.x / self.image_size.y;
-                                        let img_height = img_width / img_ratio;
-                                        (img_width, img_height)
-                                    } else {
-                                        let img_height = ui.available_height();
-                                        let img_ratio = self.image_size.y / self.image_size.x;
-                                        let img_width = img_height / img_ratio;
-                                        (img_width, img_height)
-                                    };
-
-                                    // 保留你的背景大小逻辑：图片尺寸 + 偏移值
-                                    let bg_width = img_width + self.left_value as f32 + self.right_value as f32;
-                                    let bg_height = img_height + self.up_value as f32 + self.down_value as f32;
-                                    let ori_bg_size = egui::Vec2::new(bg_width, bg_height);
-                                    let ori_display_size = egui::Vec2::new(img_width, img_height);
-
-                                    // 获取可用显示区域
-                                    let max_available_width = ui.available_width();
-                                    let max_available_height = ui.available_height();
-
-                                    // 计算整体缩放比例：让背景+图片刚好适配可用区域，不超出
-                                    // 缩放逻辑：取宽、高方向缩放比例的最小值（确保整体能完全放入可用区域）
-                                    let scale_x = if ori_bg_size.x > 0.0 { max_available_width / ori_bg_size.x } else { 1.0 };
-                                    let scale_y = if ori_bg_size.y > 0.0 { max_available_height / ori_bg_size.y } else { 1.0 };
-                                    let scale_factor = scale_x.min(scale_y).min(1.0); // 不放大（scale<=1），只缩小
-
-                                    // 应用缩放：背景、图片、偏移值同步缩放，保持相对位置不变
-                                    let scaled_bg_size = ori_bg_size * scale_factor;
-                                    let scaled_display_size = ori_display_size * scale_factor;
-                                    let scaled_left = self.left_value as f32 * scale_factor; // 缩放后的水平偏移
-                                    let scaled_up = self.up_value as f32 * scale_factor;   // 缩放后的垂直偏移
-
-                                    
-                                    // 分配背景区域
-                                    let (bg_rect, _) = ui.allocate_exact_size(
-                                        scaled_bg_size,
-                                        egui::Sense::hover()
-                                    );
-
-                                    // 绘制背景框
-                                    ui.painter().rect_filled(
-                                        bg_rect,
-                                        0.0,
-                                        self.bg_color
-                                    );
-
-                                    // 计算图片位置：基于缩放后的偏移，确保图片始终在背景框内
-                                    // 图片左上角 = 背景左上角 + 缩放后的偏移值
-                                    let image_pos = egui::Pos2::new(
-                                        bg_rect.min.x + scaled_left,
-                                        bg_rect.min.y + scaled_up
-                                    );
-                                    let image_rect = egui::Rect::from_min_size(image_pos, scaled_display_size);
-
-                                    // 绘制图片
-                                    ui.painter().image(
-                                        texture.id(),
-                                        image_rect,
-                                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
-                                        egui::Color32::WHITE, // 色调
-                                    );
-
-                                    // ========== 新增：计算文字区域矩形 + 绘制文字 ==========
-                                    // 文字区域矩形：宽度=scaled_bg_size.x，高度=scaled_bg_size.y - scaled_display_size.y
-                                    // 位置：背景框内，图片下方的区域
-                                    let text_rect = egui::Rect::from_min_max(
-                                        // 左上角：背景左边界，图片底部（背景上边界 + 图片垂直偏移 + 图片高度）
-                                        egui::Pos2::new(
-                                            bg_rect.min.x, 
-                                            bg_rect.min.y + scaled_up + scaled_display_size.y
-                                        ),
-                                        // 右下角：背景右边界，背景下边界
-                                        egui::Pos2::new(
-                                            bg_rect.min.x + scaled_bg_size.x, 
-                                            bg_rect.min.y + scaled_bg_size.y
-                                        )
-                                    );
-
-                                    // 1. 可选：绘制文字区域的边框（便于调试，可删除）
-                                    // ui.painter().rect_stroke(
-                                    //     text_rect,
-                                    //     egui::Rounding::ZERO, // 使用 egui::Rounding::ZERO 替代 Rounding::none()
-                                    //     egui::Stroke::new(1.0f32, egui::Color32::GRAY),
-                                    //     egui::StrokeKind::Outside
-                                    // );
-
-
-                                    // 2. 准备要显示的EXIF文字（和导出逻辑保持一致）
-                                    let mut text_lines = Vec::new();
-                                    if let Some(camera_model) = self.rows.iter().find(|r| r.tag_name == "相机型号") {
-                                        text_lines.push(format!("{}", camera_model.tag_value));
-                                    }
-                                    if let Some(iso) = self.rows.iter().find(|r| r.tag_name == "ISO") {
-                                        text_lines.push(format!("ISO{}", iso.tag_value));
-                                    }
-                                    if let Some(aperture) = self.rows.iter().find(|r| r.tag_name == "光圈") {
-                                        text_lines.push(format!("{}", aperture.tag_value));
-                                    }
-                                    if let Some(exposure) = self.rows.iter().find(|r| r.tag_name == "曝光时长") {
-                                        text_lines.push(format!("{}", exposure.tag_value));
-                                    }
-                                    if let Some(focal_length) = self.rows.iter().find(|r| r.tag_name == "焦距") {
-                                        text_lines.push(format!("{}", focal_length.tag_value));
-                                    }
-
-                                    // 兜底：无数据时显示提示
-                                    if text_lines.is_empty() {
-                                        text_lines.push("无EXIF信息".to_string());
-                                    }
-
-                                    // 3. 计算文字颜色（与背景对比）
-                                    let (r, g, b, _) = self.bg_color.to_tuple();
-                                    let brightness = (r as f32 * 0.299 + g as f32 * 0.587 + b as f32 * 0.114) / 255.0;
-                                    let text_color = if brightness > 0.5 {
-                                        egui::Color32::BLACK
-                                    } else {
-                                        egui::Color32::WHITE
-                                    };
-
-                                    // 4. 绘制文字到预览区域
-                                    let text_area_height = text_rect.height();
-                                    let font_size = (text_area_height * 0.6).max(12.0); // 最小字号12
-                                    let text_spacing = if text_lines.len() > 0 {
-                                        text_rect.width() / text_lines.len() as f32
-                                    } else {
-                                        0.0
-                                    };
-
-                                    for (i, text) in text_lines.iter().enumerate() {
-                                        // 文字居中定位
-                                        let text_x = text_rect.min.x + (i as f32 * text_spacing) + text_spacing / 2.0;
-                                        let text_y = text_rect.center().y;
-
-                                        // 绘制文字
-                                        ui.painter().text(
-                                            egui::Pos2::new(text_x, text_y),
-                                            egui::Align2::CENTER_CENTER,
-                                            text,
-                                            egui::FontId::new(font_size, egui::FontFamily::Proportional),
-                                            text_color
-                                        );
-                                    }
-
-                                    // 替代方案（简化模糊占位，保留开关逻辑）
-                                    // if self.enable_blur_bg {
-                                    //     // egui 0.33 需通过 glow 上下文直接操作，此处先保留开关，后续单独实现
-                                    //     ui.painter().rect_filled(
-                                    //         bg_rect,
-                                    //         egui::Rounding::ZERO,
-                                    //         egui::Color32::from_rgba_premultiplied(
-                                    //             self.bg_color.r(),
-                                    //             self.bg_color.g(),
-                                    //             self.bg_color.b(),
-                                    //             (self.bg_color.a() as f32 * 0.8) as u8 // 模拟模糊透明度
-                                    //         )
-                                    //     );
-                                    // }
-
-                                });
-                            }
-                        );
-
-                        // 在图片区域和信息区域之间添加分割线
-                        ui.separator();
-                        
-                        // 信息显示区域 - 固定在底部15%
-                        ui.scope_builder(
-                            egui::UiBuilder::new()
-                                .max_rect(egui::Rect::from_min_size(
-                                    ui.available_rect_before_wrap().min,
-                                    egui::vec2(ui.available_width(), info_height)
-                                )),
-                            |ui| {
-                                // 使用垂直居中布局确保内容不被遮挡
-                                ui.vertical_centered(|ui| {
-                                    // 显示图片尺寸信息
-                                    ui.horizontal(|ui| {
-                                        custom_text(ui, "图片尺寸：", "label", {
-                                            Some(TextOptions {
-                                                size: Some(16.0),
-                                                color: Some(Vector4::new(200, 200, 200, 255)),
-                                                align: "LEFT"
-                                            })
-                                        });
-                                        custom_text(ui, &format!("{} x {}", self.image_size.x, self.image_size.y), "label", {
-                                            Some(TextOptions {
-                                                size: Some(16.0),
-                                                color: Some(Vector4::new(200, 200, 200, 255)),
-                                                align: "LEFT"
-                                            })
-                                        });
-                                    });
-                                    
-                                    // 显示文件路径信息
-                                    ui.horizontal(|ui| {
-                                        custom_text(ui, "文件路径：", "label", {
-                                            Some(TextOptions {
-                                                size: Some(16.0),
-                                                color: Some(Vector4::new(200, 200, 200, 255)),
-                                                align: "LEFT"
-                                            })
-                                        });
-                                        if let Some(file) = &self.selected_file {
-                                            // 如果路径太长，进行截断显示
-                                            let display_path = if file.len() > 80 {
-                                                format!("...{}", &file[file.len()-77..])
-                                            } else {
-                                                file.clone()
-                                            };
-                                            custom_text(ui, &display_path, "label", {
-                                                Some(TextOptions {
-                                                    size: Some(16.0),
-                                                    color: Some(Vector4::new(200, 200, 200, 255)),
-                                                    align: "LEFT"
-                                                })
-                                            });
-                                        } else {
-                                            custom_text(ui, "未选择文件", "label", {
-                                                Some(TextOptions {
-                                                    size: Some(16.0),
-                                                    color: Some(Vector4::new(200, 200, 200, 255)),
-                                                    align: "LEFT"
-                                                })
-                                            });
-                                        }
-                                    });
-                                });
-                            }
-                        );
+                if self.dock_state.iter_all_tabs().next().is_some() {
+                    custom_text(ui, "图片预览:", "heading",
+                    Some(TextOptions {
+                        size: Some(24.0),
+                        color: None,
+                        align: "LEFT",
+                    }));
+                    self.show_loading_status(ui);
+                    ui.separator();
+
+                    // 每张图片一个标签页，可拖动分屏对比；绘制时临时取出布局，避免与 self 的借用冲突
+                    let mut dock_state = std::mem::replace(&mut self.dock_state, DockState::new(Vec::new()));
+                    DockArea::new(&mut dock_state)
+                        .style(egui_dock::Style::from_egui(ui.style()))
+                        .show_inside(ui, &mut DocTabViewer { app: self });
+                    self.dock_state = dock_state;
+                    self.sync_active_tab();
+                } else if self.pending_load.is_some() {
+                    // 第一张图片加载中
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(ui.available_height() * 0.45);
+                        self.show_loading_status(ui);
                     });
                 } else {
                     // 无图片时显示提示
@@ -1706,6 +4584,9 @@ impl eframe::App for MyEguiApp {
                 }
                 // 注意：不要在每帧都无条件重置导出提示和计时器，这会覆盖实际触发状态。
             });
+
+        // 本帧的编辑完成后记录历史
+        self.track_history(ctx);
     }
 }
 
@@ -1725,11 +4606,63 @@ fn main() -> Result<(), eframe::Error> {
         "EXIF图片编辑器",
         native_options,
         Box::new(|cc| {
+            // 创建应用实例，恢复上次保存的字体选择和会话状态
+            let mut app = MyEguiApp::default();
+            if let Some(settings) = cc.storage.and_then(|storage| eframe::get_value::<FontSettings>(storage, FONT_SETTINGS_KEY)) {
+                app.apply_font_settings(settings);
+            }
+            if let Some(session) = cc.storage.and_then(|storage| eframe::get_value::<SessionState>(storage, SESSION_KEY)) {
+                app.restore_session(session);
+            }
+            app.doc.history.reset("初始状态", app.edit_state());
+
             // 设置字体和样式
-            setup_fonts_and_style(&cc.egui_ctx);
-            
-            // 创建应用实例
-            Ok(Box::new(MyEguiApp::default()))
+            setup_fonts_and_style(&cc.egui_ctx, &app.font_library, app.ui_font_family.as_deref());
+
+            Ok(Box::new(app))
         }),
     )
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sides(borders: Borders) -> [u32; 4] {
+        [borders.up, borders.down, borders.left, borders.right]
+    }
+
+    #[test]
+    fn canvas_borders_centered_with_margin() {
+        // 3000x2000 横图放进 1:1 画布，5% 留白 = 150 像素
+        let borders = compute_canvas_borders(3000, 2000, (1, 1), 5.0, (0.0, 0.0));
+        assert_eq!(sides(borders), [650, 650, 150, 150]);
+        assert_eq!(3000 + borders.left + borders.right, 2000 + borders.up + borders.down);
+    }
+
+    #[test]
+    fn canvas_borders_follow_offset() {
+        let top_left = compute_canvas_borders(3000, 2000, (1, 1), 5.0, (-1.0, -1.0));
+        assert_eq!(sides(top_left), [150, 1150, 150, 150]);
+        let bottom_right = compute_canvas_borders(3000, 2000, (1, 1), 5.0, (1.0, 1.0));
+        assert_eq!(sides(bottom_right), [1150, 150, 150, 150]);
+        // 超出范围的偏移按 ±1 处理
+        let clamped = compute_canvas_borders(3000, 2000, (1, 1), 5.0, (0.0, 3.0));
+        assert_eq!(sides(clamped), sides(bottom_right));
+    }
+
+    #[test]
+    fn canvas_borders_reach_target_ratio() {
+        // 无留白时只扩展较短的一边
+        let portrait = compute_canvas_borders(1000, 1000, (4, 5), 0.0, (0.0, 0.0));
+        assert_eq!(sides(portrait), [125, 125, 0, 0]);
+
+        // 竖图放进 16:9 画布，宽度向上取整
+        let wide = compute_canvas_borders(2000, 3000, (16, 9), 0.0, (0.0, 0.0));
+        assert_eq!(sides(wide), [0, 0, 1667, 1667]);
+        assert_eq!(2000 + wide.left + wide.right, 5334);
+
+        // 比例为 0 时不会除零
+        let degenerate = compute_canvas_borders(800, 600, (0, 0), 0.0, (0.0, 0.0));
+        assert_eq!(sides(degenerate), [100, 100, 0, 0]);
+    }
+}
@@ -0,0 +1,72 @@
+// ========== 照片调色板提取 ==========
+// 在缩小的副本上做中位切分（median cut），得到照片的代表色，
+// 供画框背景、文字颜色和色板条使用。
+
+use image::DynamicImage;
+
+/// 提取照片的调色板，按像素占比从高到低排序
+/// @param image - 原始图片
+/// @param count - 需要的颜色数量
+pub fn extract_palette(image: &DynamicImage, count: usize) -> Vec<[u8; 3]> {
+    // 缩小到 128 像素以内再统计，速度与图片分辨率无关
+    let small = image.thumbnail(128, 128).to_rgb8();
+    let pixels: Vec<[u8; 3]> = small.pixels().map(|p| p.0).collect();
+    if pixels.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        // 选择 (颜色范围 × 像素数) 最大的盒子进行切分
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range as usize * b.len())
+            })
+            .filter(|(_, _, score)| *score > 0)
+            .max_by_key(|(_, _, score)| *score)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break; // 所有盒子都只剩单一颜色，无法继续切分
+        };
+
+        let mut target = boxes.swap_remove(index);
+        target.sort_unstable_by_key(|p| p[channel]);
+        let upper = target.split_off(target.len() / 2);
+        boxes.push(target);
+        boxes.push(upper);
+    }
+
+    boxes.sort_by_key(|b| std::cmp::Reverse(b.len()));
+    boxes.iter().map(|b| average(b)).collect()
+}
+
+// 返回取值范围最大的通道及其范围
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    let mut min = [u8::MAX; 3];
+    let mut max = [u8::MIN; 3];
+    for p in pixels {
+        for c in 0..3 {
+            min[c] = min[c].min(p[c]);
+            max[c] = max[c].max(p[c]);
+        }
+    }
+    (0..3)
+        .map(|c| (c, max[c] - min[c]))
+        .max_by_key(|(_, range)| *range)
+        .unwrap_or((0, 0))
+}
+
+fn average(pixels: &[[u8; 3]]) -> [u8; 3] {
+    let count = pixels.len().max(1) as u64;
+    let mut sum = [0u64; 3];
+    for p in pixels {
+        for c in 0..3 {
+            sum[c] += p[c] as u64;
+        }
+    }
+    sum.map(|c| (c / count) as u8)
+}
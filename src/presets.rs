@@ -0,0 +1,171 @@
+// ========== 画框预设 ==========
+// 命名的画框配置（边框、背景、文字风格、字体），
+// 可导出为 JSON 文件在团队中共享，再导入到其他人的程序中。
+// 纹理背景的图片路径在导出时改为相对预设文件所在文件夹的路径（不在其中时只保留文件名），
+// 导入时再按预设文件所在文件夹还原，不把本机的绝对路径写进共享文件。
+
+use std::path::Path;
+
+use eframe::egui::Color32;
+use serde::{Deserialize, Serialize};
+
+use crate::{BackgroundKind, BorderMode, CanvasRatio, FrameSettings, FrameTemplate};
+
+// 预设文件格式版本，格式变化时递增
+const PRESET_FILE_VERSION: u32 = 1;
+
+/// 一个命名的画框预设
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    pub frame: FrameSettings,
+    #[serde(default)]
+    pub export_font: Option<String>, // 画框文字的默认字体族
+}
+
+// 预设文件内容
+#[derive(Serialize, Deserialize)]
+struct PresetFile {
+    version: u32,
+    presets: Vec<Preset>,
+}
+
+/// 内置的示例预设
+pub fn builtin_presets() -> Vec<Preset> {
+    // Instagram 白框：1:1 画布，白色留白，极简文字
+    let instagram = FrameSettings {
+        border_mode: BorderMode::AspectRatio,
+        canvas_ratio: CanvasRatio::Square,
+        canvas_margin_percent: 5.0,
+        frame_template: FrameTemplate::Minimal,
+        field_styles: FrameTemplate::Minimal.field_styles(),
+        ..FrameSettings::default()
+    };
+
+    // 暗色画廊：深灰背景，圆角照片加投影
+    let gallery = FrameSettings {
+        border_mode: BorderMode::Percent,
+        up_percent: 6.0,
+        left_percent: 6.0,
+        right_percent: 6.0,
+        down_percent: 14.0,
+        bg_color: Color32::from_rgb(24, 24, 24),
+        corner_radius_percent: 1.0,
+        shadow_enabled: true,
+        shadow_opacity: 0.6,
+        ..FrameSettings::default()
+    };
+
+    // 富士胶片条：黑色边框，橙色醒目文字
+    let film = FrameSettings {
+        border_mode: BorderMode::Percent,
+        up_percent: 3.0,
+        left_percent: 3.0,
+        right_percent: 3.0,
+        down_percent: 12.0,
+        bg_kind: BackgroundKind::Solid,
+        bg_color: Color32::BLACK,
+        text_color: Some(Color32::from_rgb(255, 170, 40)),
+        frame_template: FrameTemplate::Bold,
+        field_styles: FrameTemplate::Bold.field_styles(),
+        ..FrameSettings::default()
+    };
+
+    [("Instagram 白框", instagram), ("暗色画廊", gallery), ("富士胶片条", film)]
+        .into_iter()
+        .map(|(name, frame)| Preset { name: name.to_string(), frame, export_font: None })
+        .collect()
+}
+
+/// 生成不与已有预设重名的名称（重名时追加序号）
+pub fn unique_name(presets: &[Preset], base: &str) -> String {
+    if !presets.iter().any(|p| p.name == base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|i| format!("{} ({})", base, i))
+        .find(|name| !presets.iter().any(|p| &p.name == name))
+        .unwrap_or_else(|| base.to_string())
+}
+
+/// 将预设写入 JSON 文件
+pub fn save_to_file(path: &Path, presets: &[Preset]) -> Result<(), String> {
+    let folder = path.parent().unwrap_or(Path::new(""));
+    let mut presets = presets.to_vec();
+    for preset in &mut presets {
+        preset.frame.bg_texture_path = preset.frame.bg_texture_path.as_deref().and_then(|texture| {
+            let texture = Path::new(texture);
+            let relative = texture.strip_prefix(folder).ok().or_else(|| texture.file_name().map(Path::new))?;
+            Some(relative.to_string_lossy().replace('\\', "/"))
+        });
+    }
+    let file = PresetFile { version: PRESET_FILE_VERSION, presets };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| format!("无法序列化预设: {}", e))?;
+    std::fs::write(path, json)
+        .map_err(|e| format!("无法写入预设文件: {}", e))
+}
+
+/// 从 JSON 文件读取预设
+pub fn load_from_file(path: &Path) -> Result<Vec<Preset>, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("无法读取预设文件: {}", e))?;
+    let file: PresetFile = serde_json::from_str(&json)
+        .map_err(|e| format!("预设文件格式错误: {}", e))?;
+    if file.version > PRESET_FILE_VERSION {
+        return Err(format!("预设文件版本 {} 高于当前程序支持的版本 {}", file.version, PRESET_FILE_VERSION));
+    }
+    let folder = path.parent().unwrap_or(Path::new(""));
+    let mut presets = file.presets;
+    for preset in &mut presets {
+        if let Some(texture) = &mut preset.frame.bg_texture_path
+            && Path::new(texture).is_relative()
+        {
+            *texture = folder.join(&*texture).display().to_string();
+        }
+    }
+    Ok(presets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preset(name: &str, texture: Option<&Path>) -> Preset {
+        let frame = FrameSettings {
+            bg_texture_path: texture.map(|path| path.display().to_string()),
+            ..FrameSettings::default()
+        };
+        Preset { name: name.to_string(), frame, export_font: None }
+    }
+
+    #[test]
+    fn unique_name_appends_counter() {
+        let presets = vec![preset("白框", None), preset("白框 (2)", None)];
+        assert_eq!(unique_name(&presets, "黑框"), "黑框");
+        assert_eq!(unique_name(&presets, "白框"), "白框 (3)");
+        assert_eq!(unique_name(&[], "白框"), "白框");
+    }
+
+    #[test]
+    fn texture_paths_are_relative_to_preset_file() {
+        let folder = std::env::temp_dir().join(format!("photo-frame-presets-{}", std::process::id()));
+        std::fs::create_dir_all(&folder).unwrap();
+        let file = folder.join("presets.json");
+        let inside = folder.join("textures").join("paper.jpg");
+        let outside = std::env::temp_dir().join("elsewhere").join("linen.png");
+
+        save_to_file(&file, &[preset("内部", Some(&inside)), preset("外部", Some(&outside)), preset("无纹理", None)]).unwrap();
+
+        // 文件中只保存相对路径，文件夹外的纹理只保留文件名
+        let saved: PresetFile = serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+        let saved: Vec<_> = saved.presets.iter().map(|p| p.frame.bg_texture_path.clone()).collect();
+        assert_eq!(saved, [Some("textures/paper.jpg".to_string()), Some("linen.png".to_string()), None]);
+
+        // 读取时以预设文件所在文件夹还原绝对路径
+        let loaded = load_from_file(&file).unwrap();
+        std::fs::remove_dir_all(&folder).unwrap();
+        let loaded: Vec<_> = loaded.iter().map(|p| p.frame.bg_texture_path.as_deref().map(Path::new)).collect();
+        assert_eq!(loaded, [Some(inside.as_path()), Some(folder.join("linen.png").as_path()), None]);
+    }
+}
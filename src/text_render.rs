@@ -0,0 +1,333 @@
+// ========== 画框文字渲染 ==========
+// 基于 ttf-parser 的文字排版与光栅化：
+// - 逐字选择字体（回退链），中英文混排时缺字自动换用下一个字体
+// - GPOS / kern 表字距调整、额外字间距
+// - 多行文字、水平对齐、基线对齐
+// - 通过 compose 的蒙版混合写入 RGB/RGBA、8/16 位缓冲区
+
+use std::sync::Arc;
+
+use ab_glyph_rasterizer::{point, Point, Rasterizer};
+use image::DynamicImage;
+use ttf_parser::{opentype_layout::LayoutTable, Face, GlyphId, OutlineBuilder, Tag};
+use ttf_parser::gpos::{PairAdjustment, PositioningSubtable};
+
+use crate::compose;
+
+/// 字体数据及其在字体集合（.ttc）中的序号
+#[derive(Clone)]
+pub struct FontSource {
+    pub data: Arc<Vec<u8>>,
+    pub index: u32,
+}
+
+impl FontSource {
+    pub fn new(data: Arc<Vec<u8>>, index: u32) -> Self {
+        Self { data, index }
+    }
+
+    pub fn face(&self) -> Option<Face<'_>> {
+        Face::parse(&self.data, self.index).ok()
+    }
+}
+
+/// 水平对齐（多行文字之间，以及整体相对锚点）
+#[derive(Clone, Copy, PartialEq)]
+pub enum HAlign {
+    Left,
+    Center,
+}
+
+/// 锚点在文字块上的垂直位置
+#[derive(Clone, Copy, PartialEq)]
+pub enum VAlign {
+    Middle,   // 整个文字块的中线
+    Baseline, // 第一行的基线
+}
+
+/// 文字样式
+#[derive(Clone, Copy)]
+pub struct TextStyle {
+    pub size: f32,           // 字号（像素，即 em 大小）
+    pub color: [u8; 4],      // 非预乘 RGBA
+    pub letter_spacing: f32, // 额外字间距（像素）
+    pub line_height: f32,    // 行高倍数
+    pub kerning: bool,       // 是否应用字距调整
+    pub h_align: HAlign,
+    pub v_align: VAlign,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.0,
+            color: [0, 0, 0, 255],
+            letter_spacing: 0.0,
+            line_height: 1.2,
+            kerning: true,
+            h_align: HAlign::Left,
+            v_align: VAlign::Baseline,
+        }
+    }
+}
+
+// 排好版的单个字形，坐标为基线原点（相对文字块左上角）
+struct PlacedGlyph {
+    font: usize,
+    id: GlyphId,
+    x: f32,
+    baseline: f32,
+}
+
+/// 排版结果
+pub struct TextLayout {
+    glyphs: Vec<PlacedGlyph>,
+    pub width: f32,   // 最宽一行的宽度（按字形前进宽度计算）
+    pub height: f32,  // 文字块高度（第一行 ascent 到最后一行 descent）
+    pub ascent: f32,  // 第一行基线到上沿的距离
+}
+
+/// 排版文字
+/// @param fonts - 字体回退链，靠前的字体优先
+/// @param text - 文字内容，`\n` 换行
+pub fn layout_text(fonts: &[FontSource], text: &str, style: &TextStyle) -> TextLayout {
+    let faces: Vec<Option<Face>> = fonts.iter().map(|f| f.face()).collect();
+    let Some(primary) = faces.iter().flatten().next() else {
+        return TextLayout { glyphs: Vec::new(), width: 0.0, height: 0.0, ascent: 0.0 };
+    };
+
+    // 行距以回退链中第一个可用字体为准，保证混排时基线一致
+    let primary_scale = style.size / primary.units_per_em() as f32;
+    let ascent = primary.ascender() as f32 * primary_scale;
+    let descent = primary.descender() as f32 * primary_scale; // 负值
+    let line_gap = primary.line_gap() as f32 * primary_scale;
+    let line_advance = (ascent - descent + line_gap) * style.line_height;
+
+    let mut glyphs = Vec::new();
+    let mut line_widths = Vec::new();
+    let mut line_ranges = Vec::new();
+
+    for (line_index, line) in text.split('\n').enumerate() {
+        let baseline = ascent + line_index as f32 * line_advance;
+        let line_start = glyphs.len();
+        let mut pen_x = 0.0;
+        let mut previous: Option<(usize, GlyphId)> = None;
+
+        for c in line.chars().filter(|c| !c.is_control()) {
+            // 逐字回退：选择第一个包含该字形的字体，都没有时使用主字体的缺字符号
+            let (font, id) = faces
+                .iter()
+                .enumerate()
+                .find_map(|(i, face)| face.as_ref().and_then(|f| f.glyph_index(c)).map(|id| (i, id)))
+                .unwrap_or_else(|| (faces.iter().position(|f| f.is_some()).unwrap_or(0), GlyphId(0)));
+            let Some(face) = faces[font].as_ref() else { continue };
+            let scale = style.size / face.units_per_em() as f32;
+
+            // 同一字体内的相邻字形才做字距调整
+            if style.kerning
+                && let Some((previous_font, previous_id)) = previous
+                && previous_font == font
+            {
+                pen_x += pair_kerning(face, previous_id, id) as f32 * scale;
+            }
+
+            glyphs.push(PlacedGlyph { font, id, x: pen_x, baseline });
+            pen_x += face.glyph_hor_advance(id).unwrap_or(0) as f32 * scale + style.letter_spacing;
+            previous = Some((font, id));
+        }
+
+        // 行尾不计算多余的字间距
+        let width = if glyphs.len() > line_start { pen_x - style.letter_spacing } else { 0.0 };
+        line_widths.push(width.max(0.0));
+        line_ranges.push(line_start..glyphs.len());
+    }
+
+    // 多行之间按水平对齐方式排列
+    let width = line_widths.iter().cloned().fold(0.0, f32::max);
+    for (range, line_width) in line_ranges.into_iter().zip(&line_widths) {
+        let shift = match style.h_align {
+            HAlign::Left => 0.0,
+            HAlign::Center => (width - line_width) / 2.0,
+        };
+        for glyph in &mut glyphs[range] {
+            glyph.x += shift;
+        }
+    }
+
+    let line_count = line_widths.len().max(1) as f32;
+    TextLayout {
+        glyphs,
+        width,
+        height: ascent - descent + (line_count - 1.0) * line_advance,
+        ascent,
+    }
+}
+
+impl TextLayout {
+    /// 根据锚点和对齐方式计算文字块左上角
+    pub fn origin(&self, anchor: (f32, f32), style: &TextStyle) -> (f32, f32) {
+        let x = match style.h_align {
+            HAlign::Left => anchor.0,
+            HAlign::Center => anchor.0 - self.width / 2.0,
+        };
+        let y = match style.v_align {
+            VAlign::Middle => anchor.1 - self.height / 2.0,
+            VAlign::Baseline => anchor.1 - self.ascent,
+        };
+        (x, y)
+    }
+}
+
+/// 把排好版的文字绘制到图片上
+/// @param anchor - 锚点坐标，含义由 style 的水平/垂直对齐决定
+pub fn draw_text(image: &mut DynamicImage, fonts: &[FontSource], layout: &TextLayout, anchor: (f32, f32), style: &TextStyle) {
+    let faces: Vec<Option<Face>> = fonts.iter().map(|f| f.face()).collect();
+    let (origin_x, origin_y) = layout.origin(anchor, style);
+    let color = [
+        style.color[0] as f32 / 255.0,
+        style.color[1] as f32 / 255.0,
+        style.color[2] as f32 / 255.0,
+    ];
+    let opacity = style.color[3] as f32 / 255.0;
+
+    for glyph in &layout.glyphs {
+        let Some(face) = faces.get(glyph.font).and_then(|f| f.as_ref()) else { continue };
+        let scale = style.size / face.units_per_em() as f32;
+        if let Some(mask) = rasterize_glyph(face, glyph.id, scale, origin_x + glyph.x, origin_y + glyph.baseline) {
+            compose::fill_mask(image, &mask, color, opacity);
+        }
+    }
+}
+
+/// 一步完成排版和绘制，返回文字块尺寸 (宽, 高)
+pub fn render_text(
+    image: &mut DynamicImage,
+    fonts: &[FontSource],
+    text: &str,
+    anchor: (f32, f32),
+    style: &TextStyle,
+) -> (f32, f32) {
+    let layout = layout_text(fonts, text, style);
+    draw_text(image, fonts, &layout, anchor, style);
+    (layout.width, layout.height)
+}
+
+// 光栅化单个字形，(x, baseline) 为字形原点的亚像素位置
+fn rasterize_glyph(face: &Face, id: GlyphId, scale: f32, x: f32, baseline: f32) -> Option<compose::Mask> {
+    let bbox = face.glyph_bounding_box(id)?;
+    let left = (x + bbox.x_min as f32 * scale).floor();
+    let top = (baseline - bbox.y_max as f32 * scale).floor();
+    let right = (x + bbox.x_max as f32 * scale).ceil();
+    let bottom = (baseline - bbox.y_min as f32 * scale).ceil();
+    let width = (right - left) as usize + 1;
+    let height = (bottom - top) as usize + 1;
+
+    let mut builder = GlyphBuilder {
+        rasterizer: Rasterizer::new(width, height),
+        scale,
+        offset: (x - left, baseline - top),
+        start: point(0.0, 0.0),
+        last: point(0.0, 0.0),
+    };
+    face.outline_glyph(id, &mut builder)?;
+
+    let mut coverage = vec![0.0; width * height];
+    builder.rasterizer.for_each_pixel(|i, alpha| coverage[i] = alpha.min(1.0));
+    Some(compose::Mask::from_coverage(left as i64, top as i64, width as u32, height as u32, coverage))
+}
+
+// 把字体轮廓（字体单位，y 轴向上）转换到像素坐标（y 轴向下）后交给光栅化器
+struct GlyphBuilder {
+    rasterizer: Rasterizer,
+    scale: f32,
+    offset: (f32, f32),
+    start: Point,
+    last: Point,
+}
+
+impl GlyphBuilder {
+    fn to_pixel(&self, x: f32, y: f32) -> Point {
+        point(self.offset.0 + x * self.scale, self.offset.1 - y * self.scale)
+    }
+}
+
+impl OutlineBuilder for GlyphBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.to_pixel(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.to_pixel(x, y);
+        self.rasterizer.draw_line(self.last, p);
+        self.last = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (p1, p) = (self.to_pixel(x1, y1), self.to_pixel(x, y));
+        self.rasterizer.draw_quad(self.last, p1, p);
+        self.last = p;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (p1, p2, p) = (self.to_pixel(x1, y1), self.to_pixel(x2, y2), self.to_pixel(x, y));
+        self.rasterizer.draw_cubic(self.last, p1, p2, p);
+        self.last = p;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.rasterizer.draw_line(self.last, self.start);
+        }
+        self.last = self.start;
+    }
+}
+
+// 两个字形之间的字距（字体单位）：优先使用 GPOS 的 kern 特性，其次使用旧式 kern 表
+fn pair_kerning(face: &Face, left: GlyphId, right: GlyphId) -> i32 {
+    if let Some(gpos) = face.tables().gpos
+        && let Some(value) = gpos_kerning(&gpos, left, right)
+    {
+        return value;
+    }
+    face.tables()
+        .kern
+        .and_then(|kern| {
+            kern.subtables
+                .into_iter()
+                .filter(|subtable| subtable.horizontal && !subtable.variable)
+                .find_map(|subtable| subtable.glyphs_kerning(left, right))
+        })
+        .map(i32::from)
+        .unwrap_or(0)
+}
+
+fn gpos_kerning(gpos: &LayoutTable, left: GlyphId, right: GlyphId) -> Option<i32> {
+    let feature = gpos.features.find(Tag::from_bytes(b"kern"))?;
+    for lookup_index in feature.lookup_indices {
+        let Some(lookup) = gpos.lookups.get(lookup_index) else { continue };
+        for subtable in lookup.subtables.into_iter::<PositioningSubtable>() {
+            let PositioningSubtable::Pair(pair) = subtable else { continue };
+            let value = match pair {
+                PairAdjustment::Format1 { coverage, sets } => coverage
+                    .get(left)
+                    .and_then(|index| sets.get(index))
+                    .and_then(|set| set.get(right))
+                    .map(|(first, _)| first.x_advance),
+                PairAdjustment::Format2 { coverage, classes, matrix } => {
+                    if coverage.contains(left) {
+                        matrix
+                            .get((classes.0.get(left), classes.1.get(right)))
+                            .map(|(first, _)| first.x_advance)
+                    } else {
+                        None
+                    }
+                }
+            };
+            if let Some(value) = value {
+                return Some(value as i32);
+            }
+        }
+    }
+    None
+}